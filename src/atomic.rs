@@ -5,12 +5,14 @@ use core::marker::PhantomData;
 use core::mem;
 use core::ptr;
 use core::ops::{Deref, DerefMut};
-use core::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+use core::slice;
 use core::sync::atomic::Ordering;
+use alloc::alloc::{alloc, dealloc, handle_alloc_error, Layout};
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 
 use guard::Guard;
-use crossbeam_utils::AtomicConsume;
+use primitive::sync::atomic::{AtomicConsume, AtomicUsize};
 
 /// Given ordering for the success case in a compare-exchange operation, returns the strongest
 /// appropriate ordering for the failure case.
@@ -25,7 +27,7 @@ fn strongest_failure_ordering(ord: Ordering) -> Ordering {
 }
 
 /// The error returned on failed compare-and-set operation.
-pub struct CompareAndSetError<'g, T: 'g, P: Pointer<T>> {
+pub struct CompareAndSetError<'g, T: ?Sized + Pointable + 'g, P: Pointer<T>> {
     /// The value in the atomic pointer at the time of the failed operation.
     pub current: Shared<'g, T>,
 
@@ -33,7 +35,11 @@ pub struct CompareAndSetError<'g, T: 'g, P: Pointer<T>> {
     pub new: P,
 }
 
-impl<'g, T: 'g, P: Pointer<T> + fmt::Debug> fmt::Debug for CompareAndSetError<'g, T, P> {
+impl<'g, T, P> fmt::Debug for CompareAndSetError<'g, T, P>
+where
+    T: ?Sized + Pointable + 'g,
+    P: Pointer<T> + fmt::Debug,
+{
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("CompareAndSetError")
             .field("current", &self.current)
@@ -88,52 +94,300 @@ impl CompareAndSetOrdering for (Ordering, Ordering) {
     }
 }
 
-/// Panics if the pointer is not properly unaligned.
+/// A bitmask containing the unused most significant bits of a pointer that hold the high tag.
+///
+/// User-space addresses on x86-64 fit into 47 bits. With 5-level paging (LA57), Linux hands out
+/// larger addresses only to processes that explicitly ask for them, so bits 48 to 63 are free.
+///
+/// On AArch64, addresses fit into 48 bits unless larger ones are explicitly asked for as well.
+/// With top byte ignore (TBI), the top byte may carry tags of a memory tagging allocator (MTE,
+/// HWASan), so only bits 48 to 55 are used and the top byte is left untouched.
+///
+/// On other targets, there is no high tag.
+#[cfg(target_arch = "x86_64")]
+const HIGH_BITS: usize = 0xffff_0000_0000_0000;
+#[cfg(target_arch = "aarch64")]
+const HIGH_BITS: usize = 0x00ff_0000_0000_0000;
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const HIGH_BITS: usize = 0;
+
+/// The number of bits in `HIGH_BITS`.
+const HIGH_TAG_BITS: u32 = HIGH_BITS.count_ones();
+
+/// The position of the least significant bit in `HIGH_BITS`.
+const HIGH_TAG_SHIFT: u32 = HIGH_BITS.trailing_zeros();
+
+/// Panics if the pointer is not properly unaligned or uses the high tag bits.
 #[inline]
-fn ensure_aligned<T>(raw: *const T) {
-    assert_eq!(raw as usize & low_bits::<T>(), 0, "unaligned pointer");
+fn ensure_aligned<T: ?Sized + Pointable>(raw: usize) {
+    assert_eq!(raw & low_bits::<T>(), 0, "unaligned pointer");
+    assert_eq!(raw & HIGH_BITS, 0, "pointer uses the high tag bits");
 }
 
 /// Returns a bitmask containing the unused least significant bits of an aligned pointer to `T`.
 #[inline]
-fn low_bits<T>() -> usize {
-    (1 << mem::align_of::<T>().trailing_zeros()) - 1
+fn low_bits<T: ?Sized + Pointable>() -> usize {
+    (1 << T::ALIGN.trailing_zeros()) - 1
 }
 
 /// Given a tagged pointer `data`, returns the same pointer, but tagged with `tag`.
 ///
-/// `tag` is truncated to fit into the unused bits of the pointer to `T`.
+/// `tag` is truncated to fit into the unused least significant bits of the pointer to `T`. The high
+/// tag is left untouched.
 #[inline]
-fn data_with_tag<T>(data: usize, tag: usize) -> usize {
+fn data_with_tag<T: ?Sized + Pointable>(data: usize, tag: usize) -> usize {
     (data & !low_bits::<T>()) | (tag & low_bits::<T>())
 }
 
-/// Decomposes a tagged pointer `data` into the pointer and the tag.
+/// Decomposes a tagged pointer `data` into the pointer and the tag, ignoring the high tag.
 #[inline]
-fn decompose_data<T>(data: usize) -> (*mut T, usize) {
-    let raw = (data & !low_bits::<T>()) as *mut T;
+fn decompose_data<T: ?Sized + Pointable>(data: usize) -> (usize, usize) {
+    let raw = data & !low_bits::<T>() & !HIGH_BITS;
     let tag = data & low_bits::<T>();
     (raw, tag)
 }
 
+/// Given a tagged pointer `data`, returns the same pointer, but with the high tag `tag`.
+///
+/// `tag` is truncated to fit into the high tag bits.
+#[inline]
+fn data_with_high_tag(data: usize, tag: usize) -> usize {
+    if HIGH_BITS == 0 {
+        data
+    } else {
+        (data & !HIGH_BITS) | ((tag << HIGH_TAG_SHIFT) & HIGH_BITS)
+    }
+}
+
+/// Returns the high tag of a tagged pointer `data`.
+#[inline]
+fn high_tag_of(data: usize) -> usize {
+    if HIGH_BITS == 0 {
+        0
+    } else {
+        (data & HIGH_BITS) >> HIGH_TAG_SHIFT
+    }
+}
+
+/// Types that can be pointed to by [`Atomic`], [`Owned`], and [`Shared`].
+///
+/// Objects are referred to by a thin pointer stored in a single word, so that the unused least
+/// significant bits of the pointer can hold a tag. Sized types are allocated just like in a `Box`.
+/// Slices are allocated together with a header holding their length, which is placed right before
+/// the elements.
+///
+/// Trait objects are not supported, since a trait object can't be recovered from a thin pointer
+/// on stable Rust. Use an `Owned<Box<Trait>>` instead.
+///
+/// # Safety
+///
+/// The pointer returned by [`init`] must be nonzero and a multiple of [`ALIGN`], and must remain
+/// valid for [`deref`] and [`deref_mut`] until it is passed to [`drop`].
+///
+/// # Examples
+///
+/// ```
+/// use crossbeam_epoch::{self as epoch, Atomic};
+/// use std::sync::atomic::Ordering::SeqCst;
+///
+/// let a = Atomic::<[i32]>::init(vec![1, 2, 3]);
+/// let guard = &epoch::pin();
+/// let p = a.load(SeqCst, guard);
+/// assert_eq!(unsafe { p.deref() }, &[1, 2, 3]);
+/// ```
+///
+/// [`Atomic`]: struct.Atomic.html
+/// [`Owned`]: struct.Owned.html
+/// [`Shared`]: struct.Shared.html
+/// [`init`]: trait.Pointable.html#tymethod.init
+/// [`ALIGN`]: trait.Pointable.html#associatedconstant.ALIGN
+/// [`deref`]: trait.Pointable.html#tymethod.deref
+/// [`deref_mut`]: trait.Pointable.html#tymethod.deref_mut
+/// [`drop`]: trait.Pointable.html#tymethod.drop
+pub unsafe trait Pointable {
+    /// The alignment of pointers to the object.
+    const ALIGN: usize;
+
+    /// The type of the value an object is initialized with.
+    type Init;
+
+    /// Allocates an object initialized with `init` and returns a pointer to it.
+    fn init(init: Self::Init) -> usize;
+
+    /// Dereferences the pointer `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `init` and not yet passed to `drop`.
+    unsafe fn deref<'a>(ptr: usize) -> &'a Self;
+
+    /// Mutably dereferences the pointer `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `init` and not yet passed to `drop`, and the object must
+    /// not be accessed through any other reference.
+    unsafe fn deref_mut<'a>(ptr: usize) -> &'a mut Self;
+
+    /// Drops the object pointed to by `ptr` and deallocates it.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `init` and not yet passed to `drop`, and the object must
+    /// not be accessed anymore.
+    unsafe fn drop(ptr: usize);
+}
+
+unsafe impl<T> Pointable for T {
+    const ALIGN: usize = mem::align_of::<T>();
+
+    type Init = T;
+
+    fn init(init: T) -> usize {
+        Box::into_raw(Box::new(init)) as usize
+    }
+
+    unsafe fn deref<'a>(ptr: usize) -> &'a T {
+        &*(ptr as *const T)
+    }
+
+    unsafe fn deref_mut<'a>(ptr: usize) -> &'a mut T {
+        &mut *(ptr as *mut T)
+    }
+
+    #[cfg(not(feature = "debug-reclaim"))]
+    unsafe fn drop(ptr: usize) {
+        drop(Box::from_raw(ptr as *mut T));
+    }
+
+    #[cfg(feature = "debug-reclaim")]
+    unsafe fn drop(ptr: usize) {
+        let ptr = ptr as *mut T;
+        ptr::drop_in_place(ptr);
+        ::debug::poison(ptr as *mut u8, mem::size_of::<T>());
+        drop(Box::from_raw(ptr as *mut mem::ManuallyDrop<T>));
+    }
+}
+
+/// The header of an allocated slice, which is immediately followed by the elements.
+#[repr(C)]
+struct Array<T> {
+    len: usize,
+    elements: [T; 0],
+}
+
+impl<T> Array<T> {
+    /// Returns the layout of an allocated slice of length `len`.
+    fn layout(len: usize) -> Layout {
+        let size = mem::size_of::<T>()
+            .checked_mul(len)
+            .and_then(|size| size.checked_add(mem::size_of::<Array<T>>()))
+            .expect("capacity overflow");
+        Layout::from_size_align(size, mem::align_of::<Array<T>>()).unwrap()
+    }
+}
+
+unsafe impl<T> Pointable for [T] {
+    const ALIGN: usize = mem::align_of::<Array<T>>();
+
+    type Init = Vec<T>;
+
+    fn init(mut init: Vec<T>) -> usize {
+        let len = init.len();
+        let layout = Array::<T>::layout(len);
+        unsafe {
+            let array = alloc(layout) as *mut Array<T>;
+            if array.is_null() {
+                handle_alloc_error(layout);
+            }
+            ptr::write(&mut (*array).len, len);
+            ptr::copy_nonoverlapping(init.as_ptr(), (*array).elements.as_mut_ptr(), len);
+
+            // The elements have been moved out of the vector.
+            init.set_len(0);
+            array as usize
+        }
+    }
+
+    unsafe fn deref<'a>(ptr: usize) -> &'a [T] {
+        let array = &*(ptr as *const Array<T>);
+        slice::from_raw_parts(array.elements.as_ptr(), array.len)
+    }
+
+    unsafe fn deref_mut<'a>(ptr: usize) -> &'a mut [T] {
+        let array = &mut *(ptr as *mut Array<T>);
+        slice::from_raw_parts_mut(array.elements.as_mut_ptr(), array.len)
+    }
+
+    unsafe fn drop(ptr: usize) {
+        let array = ptr as *mut Array<T>;
+        let len = (*array).len;
+        ptr::drop_in_place(Self::deref_mut(ptr));
+        #[cfg(feature = "debug-reclaim")]
+        ::debug::poison(array as *mut u8, Array::<T>::layout(len).size());
+        dealloc(array as *mut u8, Array::<T>::layout(len));
+    }
+}
+
 /// An atomic pointer that can be safely shared between threads.
 ///
 /// The pointer must be properly aligned. Since it is aligned, a tag can be stored into the unused
 /// least significant bits of the address.  More precisely, a tag should be less than `(1 <<
 /// mem::align_of::<T>().trailing_zeros())`.
 ///
+/// On x86-64 and AArch64, a separate high tag of [`HIGH_TAG_BITS`] bits can be stored into the
+/// unused most significant bits of the address with [`Shared::with_high_tag`]. The high tag is
+/// independent of the tag: [`Shared::tag`] and [`Shared::with_tag`] never read or change it.
+///
 /// Any method that loads the pointer must be passed a reference to a [`Guard`].
 ///
+/// The pointee can also be a slice, see [`Pointable`].
+///
 /// [`Guard`]: struct.Guard.html
-pub struct Atomic<T> {
+/// [`Pointable`]: trait.Pointable.html
+/// [`HIGH_TAG_BITS`]: struct.Atomic.html#associatedconstant.HIGH_TAG_BITS
+/// [`Shared::with_high_tag`]: struct.Shared.html#method.with_high_tag
+/// [`Shared::tag`]: struct.Shared.html#method.tag
+/// [`Shared::with_tag`]: struct.Shared.html#method.with_tag
+pub struct Atomic<T: ?Sized + Pointable> {
     data: AtomicUsize,
     _marker: PhantomData<*mut T>,
 }
 
-unsafe impl<T: Send + Sync> Send for Atomic<T> {}
-unsafe impl<T: Send + Sync> Sync for Atomic<T> {}
+unsafe impl<T: ?Sized + Pointable + Send + Sync> Send for Atomic<T> {}
+unsafe impl<T: ?Sized + Pointable + Send + Sync> Sync for Atomic<T> {}
 
 impl<T> Atomic<T> {
+    /// Allocates `value` on the heap and returns a new atomic pointer pointing to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::Atomic;
+    ///
+    /// let a = Atomic::new(1234);
+    /// ```
+    pub fn new(value: T) -> Atomic<T> {
+        Self::init(value)
+    }
+}
+
+impl<T: ?Sized + Pointable> Atomic<T> {
+    /// The number of bits available for the tag.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::Atomic;
+    ///
+    /// assert_eq!(Atomic::<u64>::TAG_BITS, 3);
+    /// ```
+    pub const TAG_BITS: u32 = T::ALIGN.trailing_zeros();
+
+    /// The number of bits available for the high tag, which is zero unless the target is x86-64
+    /// or AArch64.
+    pub const HIGH_TAG_BITS: u32 = HIGH_TAG_BITS;
+
     /// Returns a new atomic pointer pointing to the tagged pointer `data`.
     fn from_usize(data: usize) -> Self {
         Self {
@@ -151,10 +405,10 @@ impl<T> Atomic<T> {
     ///
     /// let a = Atomic::<i32>::null();
     /// ```
-    #[cfg(not(feature = "nightly"))]
+    #[cfg(any(not(feature = "nightly"), crossbeam_loom))]
     pub fn null() -> Atomic<T> {
         Self {
-            data: ATOMIC_USIZE_INIT,
+            data: AtomicUsize::new(0),
             _marker: PhantomData,
         }
     }
@@ -168,25 +422,25 @@ impl<T> Atomic<T> {
     ///
     /// let a = Atomic::<i32>::null();
     /// ```
-    #[cfg(feature = "nightly")]
+    #[cfg(all(feature = "nightly", not(crossbeam_loom)))]
     pub const fn null() -> Atomic<T> {
         Self {
-            data: ATOMIC_USIZE_INIT,
+            data: AtomicUsize::new(0),
             _marker: PhantomData,
         }
     }
 
-    /// Allocates `value` on the heap and returns a new atomic pointer pointing to it.
+    /// Allocates an object initialized with `init` and returns a new atomic pointer pointing to it.
     ///
     /// # Examples
     ///
     /// ```
     /// use crossbeam_epoch::Atomic;
     ///
-    /// let a = Atomic::new(1234);
+    /// let a = Atomic::<[u8]>::init(vec![1, 2, 3]);
     /// ```
-    pub fn new(value: T) -> Atomic<T> {
-        Self::from(Owned::new(value))
+    pub fn init(init: T::Init) -> Atomic<T> {
+        Self::from(Owned::init(init))
     }
 
     /// Loads a `Shared` from the atomic pointer.
@@ -397,6 +651,144 @@ impl<T> Atomic<T> {
             })
     }
 
+    /// Fetches the pointer and applies a function to it that optionally returns a new pointer
+    /// (either `Shared` or `Owned`). If the function returns `Some`, the new pointer is stored with
+    /// a compare-and-set. If the pointer has been changed by another thread in the meantime, the
+    /// function is called again with the actual current pointer.
+    ///
+    /// The second argument of the function is the pointer it returned in the previous failed
+    /// attempt, or `None` on the first call, so that an `Owned` can be reused instead of
+    /// allocating again. See also [`fetch_update_owned`].
+    ///
+    /// Returns the previous and the new pointer if the new pointer was written, or the current
+    /// pointer if the function returned `None`.
+    ///
+    /// This method takes a [`CompareAndSetOrdering`] argument which describes the memory
+    /// ordering of the compare-and-set. The pointer is loaded with the failure ordering.
+    ///
+    /// [`fetch_update_owned`]: struct.Atomic.html#method.fetch_update_owned
+    /// [`CompareAndSetOrdering`]: trait.CompareAndSetOrdering.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, Atomic, Owned};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let a = Atomic::new(1);
+    /// let guard = &epoch::pin();
+    ///
+    /// let res = a.fetch_update(SeqCst, guard, |p, rejected| {
+    ///     let n = unsafe { p.deref() };
+    ///     let mut new = rejected.unwrap_or_else(|| Owned::new(0));
+    ///     *new = n + 1;
+    ///     Some(new)
+    /// });
+    /// let (prev, new) = res.unwrap();
+    /// assert_eq!(unsafe { new.deref() }, &2);
+    /// unsafe { guard.defer_destroy(prev); }
+    ///
+    /// let res = a.fetch_update(SeqCst, guard, |p, _| {
+    ///     if p.is_null() { Some(Owned::new(0)) } else { None }
+    /// });
+    /// assert_eq!(res, Err(new));
+    /// # unsafe { drop(new.into_owned()); } // avoid leak
+    /// ```
+    pub fn fetch_update<'g, O, P, F>(
+        &self,
+        ord: O,
+        guard: &'g Guard,
+        mut f: F,
+    ) -> Result<(Shared<'g, T>, Shared<'g, T>), Shared<'g, T>>
+    where
+        O: CompareAndSetOrdering,
+        P: Pointer<T>,
+        F: FnMut(Shared<'g, T>, Option<P>) -> Option<P>,
+    {
+        let ord = (ord.success(), ord.failure());
+        let mut current = self.load(ord.1, guard);
+        let mut rejected = None;
+        while let Some(new) = f(current, rejected) {
+            match self.compare_and_set(current, new, ord, guard) {
+                Ok(new) => return Ok((current, new)),
+                Err(err) => {
+                    current = err.current;
+                    rejected = Some(err.new);
+                }
+            }
+        }
+        Err(current)
+    }
+
+    /// Fetches the pointer and applies a function to it and the object owned by `new`, which
+    /// prepares the object to replace the current pointer. If the function returns `true`, `new`
+    /// is stored with a compare-and-set. If the pointer has been changed by another thread in the
+    /// meantime, the function is called again with the actual current pointer and the same object.
+    ///
+    /// This is a shorthand for [`fetch_update`] when a single allocation is prepared up front.
+    ///
+    /// Returns the previous and the new pointer if `new` was written, or gives `new` back if the
+    /// function returned `false`.
+    ///
+    /// This method takes a [`CompareAndSetOrdering`] argument which describes the memory
+    /// ordering of the compare-and-set. The pointer is loaded with the failure ordering.
+    ///
+    /// [`fetch_update`]: struct.Atomic.html#method.fetch_update
+    /// [`CompareAndSetOrdering`]: trait.CompareAndSetOrdering.html
+    ///
+    /// # Examples
+    ///
+    /// Pushing onto a linked stack:
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, Atomic, Owned};
+    /// use std::sync::atomic::Ordering::{Relaxed, Release};
+    ///
+    /// struct Node {
+    ///     value: i32,
+    ///     next: Atomic<Node>,
+    /// }
+    ///
+    /// let head = Atomic::null();
+    /// let guard = &epoch::pin();
+    ///
+    /// let node = Owned::new(Node { value: 1, next: Atomic::null() });
+    /// let res = head.fetch_update_owned(node, Release, guard, |head, node| {
+    ///     node.next.store(head, Relaxed);
+    ///     true
+    /// });
+    /// let (prev, new) = res.unwrap();
+    /// assert!(prev.is_null());
+    /// assert_eq!(unsafe { new.deref() }.value, 1);
+    /// # unsafe { drop(new.into_owned()); } // avoid leak
+    /// ```
+    pub fn fetch_update_owned<'g, O, F>(
+        &self,
+        mut new: Owned<T>,
+        ord: O,
+        guard: &'g Guard,
+        mut f: F,
+    ) -> Result<(Shared<'g, T>, Shared<'g, T>), Owned<T>>
+    where
+        O: CompareAndSetOrdering,
+        F: FnMut(Shared<'g, T>, &mut T) -> bool,
+    {
+        let ord = (ord.success(), ord.failure());
+        let mut current = self.load(ord.1, guard);
+        loop {
+            if !f(current, &mut new) {
+                return Err(new);
+            }
+            match self.compare_and_set(current, new, ord, guard) {
+                Ok(new) => return Ok((current, new)),
+                Err(err) => {
+                    current = err.current;
+                    new = err.new;
+                }
+            }
+        }
+    }
+
     /// Bitwise "and" with the current tag.
     ///
     /// Performs a bitwise "and" operation on the current tag and the argument `val`, and sets the
@@ -473,27 +865,27 @@ impl<T> Atomic<T> {
     }
 }
 
-impl<T> fmt::Debug for Atomic<T> {
+impl<T: ?Sized + Pointable> fmt::Debug for Atomic<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let data = self.data.load(Ordering::SeqCst);
         let (raw, tag) = decompose_data::<T>(data);
 
         f.debug_struct("Atomic")
-            .field("raw", &raw)
+            .field("raw", &(raw as *const ()))
             .field("tag", &tag)
             .finish()
     }
 }
 
-impl<T> fmt::Pointer for Atomic<T> {
+impl<T: ?Sized + Pointable> fmt::Pointer for Atomic<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let data = self.data.load(Ordering::SeqCst);
         let (raw, _) = decompose_data::<T>(data);
-        fmt::Pointer::fmt(&raw, f)
+        fmt::Pointer::fmt(&(raw as *const ()), f)
     }
 }
 
-impl<T> Clone for Atomic<T> {
+impl<T: ?Sized + Pointable> Clone for Atomic<T> {
     /// Returns a copy of the atomic value.
     ///
     /// Note that a `Relaxed` load is used here. If you need synchronization, use it with other
@@ -504,13 +896,13 @@ impl<T> Clone for Atomic<T> {
     }
 }
 
-impl<T> Default for Atomic<T> {
+impl<T: ?Sized + Pointable> Default for Atomic<T> {
     fn default() -> Self {
         Atomic::null()
     }
 }
 
-impl<T> From<Owned<T>> for Atomic<T> {
+impl<T: ?Sized + Pointable> From<Owned<T>> for Atomic<T> {
     /// Returns a new atomic pointer pointing to `owned`.
     ///
     /// # Examples
@@ -539,7 +931,7 @@ impl<T> From<T> for Atomic<T> {
     }
 }
 
-impl<'g, T> From<Shared<'g, T>> for Atomic<T> {
+impl<'g, T: ?Sized + Pointable> From<Shared<'g, T>> for Atomic<T> {
     /// Returns a new atomic pointer pointing to `ptr`.
     ///
     /// # Examples
@@ -566,12 +958,14 @@ impl<T> From<*const T> for Atomic<T> {
     /// let a = Atomic::<i32>::from(ptr::null::<i32>());
     /// ```
     fn from(raw: *const T) -> Self {
+        #[cfg(feature = "debug-reclaim")]
+        ::debug::allocated(raw as usize);
         Self::from_usize(raw as usize)
     }
 }
 
 /// A trait for either `Owned` or `Shared` pointers.
-pub trait Pointer<T> {
+pub trait Pointer<T: ?Sized + Pointable> {
     /// Returns the machine representation of the pointer.
     fn into_usize(self) -> usize;
 
@@ -584,13 +978,19 @@ pub trait Pointer<T> {
 /// This type is very similar to `Box<T>`.
 ///
 /// The pointer must be properly aligned. Since it is aligned, a tag can be stored into the unused
-/// least significant bits of the address.
-pub struct Owned<T> {
+/// least significant bits of the address, and a high tag into the unused most significant bits.
+/// See [`Atomic`] for details.
+///
+/// The pointee can also be a slice, see [`Pointable`].
+///
+/// [`Atomic`]: struct.Atomic.html
+/// [`Pointable`]: trait.Pointable.html
+pub struct Owned<T: ?Sized + Pointable> {
     data: usize,
     _marker: PhantomData<Box<T>>,
 }
 
-impl<T> Pointer<T> for Owned<T> {
+impl<T: ?Sized + Pointable> Pointer<T> for Owned<T> {
     #[inline]
     fn into_usize(self) -> usize {
         let data = self.data;
@@ -624,7 +1024,7 @@ impl<T> Owned<T> {
     /// let o = Owned::new(1234);
     /// ```
     pub fn new(value: T) -> Owned<T> {
-        Self::from(Box::new(value))
+        Self::init(value)
     }
 
     /// Returns a new owned pointer pointing to `raw`.
@@ -645,11 +1045,13 @@ impl<T> Owned<T> {
     /// let o = unsafe { Owned::from_raw(Box::into_raw(Box::new(1234))) };
     /// ```
     pub unsafe fn from_raw(raw: *mut T) -> Owned<T> {
-        ensure_aligned(raw);
+        ensure_aligned::<T>(raw as usize);
+        #[cfg(feature = "debug-reclaim")]
+        ::debug::allocated(raw as usize);
         Self::from_usize(raw as usize)
     }
 
-    /// Converts the owned pointer into a [`Shared`].
+    /// Converts the owned pointer into a `Box`.
     ///
     /// # Examples
     ///
@@ -657,16 +1059,43 @@ impl<T> Owned<T> {
     /// use crossbeam_epoch::{self as epoch, Owned};
     ///
     /// let o = Owned::new(1234);
-    /// let guard = &epoch::pin();
-    /// let p = o.into_shared(guard);
+    /// let b: Box<i32> = o.into_box();
+    /// assert_eq!(*b, 1234);
     /// ```
+    pub fn into_box(self) -> Box<T> {
+        let (raw, _) = decompose_data::<T>(self.data);
+        mem::forget(self);
+        unsafe { Box::from_raw(raw as *mut T) }
+    }
+}
+
+impl<T: ?Sized + Pointable> Owned<T> {
+    /// The number of bits available for the tag.
+    pub const TAG_BITS: u32 = T::ALIGN.trailing_zeros();
+
+    /// The number of bits available for the high tag, which is zero unless the target is x86-64
+    /// or AArch64.
+    pub const HIGH_TAG_BITS: u32 = HIGH_TAG_BITS;
+
+    /// Allocates an object initialized with `init` and returns a new owned pointer pointing to it.
     ///
-    /// [`Shared`]: struct.Shared.html
-    pub fn into_shared<'g>(self, _: &'g Guard) -> Shared<'g, T> {
-        unsafe { Shared::from_usize(self.into_usize()) }
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::Owned;
+    ///
+    /// let o = Owned::<[i32]>::init(vec![1, 2, 3]);
+    /// assert_eq!(*o, [1, 2, 3]);
+    /// ```
+    pub fn init(init: T::Init) -> Owned<T> {
+        let raw = T::init(init);
+        ensure_aligned::<T>(raw);
+        #[cfg(feature = "debug-reclaim")]
+        ::debug::allocated(raw);
+        unsafe { Self::from_usize(raw) }
     }
 
-    /// Converts the owned pointer into a `Box`.
+    /// Converts the owned pointer into a [`Shared`].
     ///
     /// # Examples
     ///
@@ -674,13 +1103,13 @@ impl<T> Owned<T> {
     /// use crossbeam_epoch::{self as epoch, Owned};
     ///
     /// let o = Owned::new(1234);
-    /// let b: Box<i32> = o.into_box();
-    /// assert_eq!(*b, 1234);
+    /// let guard = &epoch::pin();
+    /// let p = o.into_shared(guard);
     /// ```
-    pub fn into_box(self) -> Box<T> {
-        let (raw, _) = decompose_data::<T>(self.data);
-        mem::forget(self);
-        unsafe { Box::from_raw(raw) }
+    ///
+    /// [`Shared`]: struct.Shared.html
+    pub fn into_shared<'g>(self, _: &'g Guard) -> Shared<'g, T> {
+        unsafe { Shared::from_usize(self.into_usize()) }
     }
 
     /// Returns the tag stored within the pointer.
@@ -698,7 +1127,8 @@ impl<T> Owned<T> {
     }
 
     /// Returns the same pointer, but tagged with `tag`. `tag` is truncated to be fit into the
-    /// unused bits of the pointer to `T`.
+    /// unused bits of the pointer to `T`, i.e. to its lowest `TAG_BITS` bits. The high tag is left
+    /// untouched.
     ///
     /// # Examples
     ///
@@ -714,23 +1144,57 @@ impl<T> Owned<T> {
         let data = self.into_usize();
         unsafe { Self::from_usize(data_with_tag::<T>(data, tag)) }
     }
+
+    /// Returns the high tag stored within the pointer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::Owned;
+    ///
+    /// assert_eq!(Owned::new(1234).high_tag(), 0);
+    /// ```
+    pub fn high_tag(&self) -> usize {
+        high_tag_of(self.data)
+    }
+
+    /// Returns the same pointer, but with the high tag `tag`. `tag` is truncated to its lowest
+    /// `HIGH_TAG_BITS` bits. The tag is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::Owned;
+    ///
+    /// let o = Owned::new(0u8).with_high_tag(0x81);
+    /// if Owned::<u8>::HIGH_TAG_BITS >= 8 {
+    ///     assert_eq!(o.high_tag(), 0x81);
+    /// }
+    /// assert_eq!(*o, 0);
+    /// ```
+    pub fn with_high_tag(self, tag: usize) -> Owned<T> {
+        let data = self.into_usize();
+        unsafe { Self::from_usize(data_with_high_tag(data, tag)) }
+    }
 }
 
-impl<T> Drop for Owned<T> {
+impl<T: ?Sized + Pointable> Drop for Owned<T> {
     fn drop(&mut self) {
         let (raw, _) = decompose_data::<T>(self.data);
+        #[cfg(feature = "debug-reclaim")]
+        ::debug::freed(raw);
         unsafe {
-            drop(Box::from_raw(raw));
+            T::drop(raw);
         }
     }
 }
 
-impl<T> fmt::Debug for Owned<T> {
+impl<T: ?Sized + Pointable> fmt::Debug for Owned<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let (raw, tag) = decompose_data::<T>(self.data);
 
         f.debug_struct("Owned")
-            .field("raw", &raw)
+            .field("raw", &(raw as *const ()))
             .field("tag", &tag)
             .finish()
     }
@@ -742,19 +1206,19 @@ impl<T: Clone> Clone for Owned<T> {
     }
 }
 
-impl<T> Deref for Owned<T> {
+impl<T: ?Sized + Pointable> Deref for Owned<T> {
     type Target = T;
 
     fn deref(&self) -> &T {
         let (raw, _) = decompose_data::<T>(self.data);
-        unsafe { &*raw }
+        unsafe { T::deref(raw) }
     }
 }
 
-impl<T> DerefMut for Owned<T> {
+impl<T: ?Sized + Pointable> DerefMut for Owned<T> {
     fn deref_mut(&mut self) -> &mut T {
         let (raw, _) = decompose_data::<T>(self.data);
-        unsafe { &mut *raw }
+        unsafe { T::deref_mut(raw) }
     }
 }
 
@@ -783,25 +1247,25 @@ impl<T> From<Box<T>> for Owned<T> {
     }
 }
 
-impl<T> Borrow<T> for Owned<T> {
+impl<T: ?Sized + Pointable> Borrow<T> for Owned<T> {
     fn borrow(&self) -> &T {
         &**self
     }
 }
 
-impl<T> BorrowMut<T> for Owned<T> {
+impl<T: ?Sized + Pointable> BorrowMut<T> for Owned<T> {
     fn borrow_mut(&mut self) -> &mut T {
         &mut **self
     }
 }
 
-impl<T> AsRef<T> for Owned<T> {
+impl<T: ?Sized + Pointable> AsRef<T> for Owned<T> {
     fn as_ref(&self) -> &T {
         &**self
     }
 }
 
-impl<T> AsMut<T> for Owned<T> {
+impl<T: ?Sized + Pointable> AsMut<T> for Owned<T> {
     fn as_mut(&mut self) -> &mut T {
         &mut **self
     }
@@ -812,13 +1276,19 @@ impl<T> AsMut<T> for Owned<T> {
 /// The pointer is valid for use only during the lifetime `'g`.
 ///
 /// The pointer must be properly aligned. Since it is aligned, a tag can be stored into the unused
-/// least significant bits of the address.
-pub struct Shared<'g, T: 'g> {
+/// least significant bits of the address, and a high tag into the unused most significant bits.
+/// See [`Atomic`] for details.
+///
+/// The pointee can also be a slice, see [`Pointable`].
+///
+/// [`Atomic`]: struct.Atomic.html
+/// [`Pointable`]: trait.Pointable.html
+pub struct Shared<'g, T: 'g + ?Sized + Pointable> {
     data: usize,
     _marker: PhantomData<(&'g (), *const T)>,
 }
 
-impl<'g, T> Clone for Shared<'g, T> {
+impl<'g, T: ?Sized + Pointable> Clone for Shared<'g, T> {
     fn clone(&self) -> Self {
         Shared {
             data: self.data,
@@ -827,9 +1297,9 @@ impl<'g, T> Clone for Shared<'g, T> {
     }
 }
 
-impl<'g, T> Copy for Shared<'g, T> {}
+impl<'g, T: ?Sized + Pointable> Copy for Shared<'g, T> {}
 
-impl<'g, T> Pointer<T> for Shared<'g, T> {
+impl<'g, T: ?Sized + Pointable> Pointer<T> for Shared<'g, T> {
     #[inline]
     fn into_usize(self) -> usize {
         self.data
@@ -845,6 +1315,56 @@ impl<'g, T> Pointer<T> for Shared<'g, T> {
 }
 
 impl<'g, T> Shared<'g, T> {
+    /// Converts the pointer to a raw pointer (without the tag).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, Atomic, Owned};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let o = Owned::new(1234);
+    /// let raw = &*o as *const _;
+    /// let a = Atomic::from(o);
+    ///
+    /// let guard = &epoch::pin();
+    /// let p = a.load(SeqCst, guard);
+    /// assert_eq!(p.as_raw(), raw);
+    /// ```
+    pub fn as_raw(&self) -> *const T {
+        let (raw, _) = decompose_data::<T>(self.data);
+        raw as *const T
+    }
+}
+
+impl<'g, T: ?Sized + Pointable> Shared<'g, T> {
+    /// The number of bits available for the tag.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::Shared;
+    ///
+    /// let p = Shared::<u64>::null().with_tag(5);
+    /// assert_eq!(Shared::<u64>::TAG_BITS, 3);
+    /// assert_eq!(p.tag(), 5);
+    /// ```
+    pub const TAG_BITS: u32 = T::ALIGN.trailing_zeros();
+
+    /// The number of bits available for the high tag, which is zero unless the target is x86-64
+    /// or AArch64.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::Shared;
+    ///
+    /// if cfg!(target_arch = "x86_64") {
+    ///     assert_eq!(Shared::<u8>::HIGH_TAG_BITS, 16);
+    /// }
+    /// ```
+    pub const HIGH_TAG_BITS: u32 = HIGH_TAG_BITS;
+
     /// Returns a new null pointer.
     ///
     /// # Examples
@@ -877,28 +1397,8 @@ impl<'g, T> Shared<'g, T> {
     /// assert!(!a.load(SeqCst, guard).is_null());
     /// ```
     pub fn is_null(&self) -> bool {
-        self.as_raw().is_null()
-    }
-
-    /// Converts the pointer to a raw pointer (without the tag).
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use crossbeam_epoch::{self as epoch, Atomic, Owned};
-    /// use std::sync::atomic::Ordering::SeqCst;
-    ///
-    /// let o = Owned::new(1234);
-    /// let raw = &*o as *const _;
-    /// let a = Atomic::from(o);
-    ///
-    /// let guard = &epoch::pin();
-    /// let p = a.load(SeqCst, guard);
-    /// assert_eq!(p.as_raw(), raw);
-    /// ```
-    pub fn as_raw(&self) -> *const T {
         let (raw, _) = decompose_data::<T>(self.data);
-        raw
+        raw == 0
     }
 
     /// Dereferences the pointer.
@@ -933,7 +1433,8 @@ impl<'g, T> Shared<'g, T> {
     /// }
     /// ```
     pub unsafe fn deref(&self) -> &'g T {
-        &*self.as_raw()
+        let (raw, _) = decompose_data::<T>(self.data);
+        T::deref(raw)
     }
 
     /// Converts the pointer to a reference.
@@ -968,7 +1469,12 @@ impl<'g, T> Shared<'g, T> {
     /// }
     /// ```
     pub unsafe fn as_ref(&self) -> Option<&'g T> {
-        self.as_raw().as_ref()
+        let (raw, _) = decompose_data::<T>(self.data);
+        if raw == 0 {
+            None
+        } else {
+            Some(T::deref(raw))
+        }
     }
 
     /// Takes ownership of the pointee.
@@ -996,10 +1502,9 @@ impl<'g, T> Shared<'g, T> {
     /// }
     /// ```
     pub unsafe fn into_owned(self) -> Owned<T> {
-        debug_assert!(
-            self.as_raw() != ptr::null(),
-            "converting a null `Shared` into `Owned`"
-        );
+        debug_assert!(!self.is_null(), "converting a null `Shared` into `Owned`");
+        #[cfg(feature = "debug-reclaim")]
+        ::debug::into_owned(decompose_data::<T>(self.data).0);
         Owned::from_usize(self.data)
     }
 
@@ -1022,7 +1527,8 @@ impl<'g, T> Shared<'g, T> {
     }
 
     /// Returns the same pointer, but tagged with `tag`. `tag` is truncated to be fit into the
-    /// unused bits of the pointer to `T`.
+    /// unused bits of the pointer to `T`, i.e. to its lowest `TAG_BITS` bits. The high tag is left
+    /// untouched.
     ///
     /// # Examples
     ///
@@ -1042,6 +1548,51 @@ impl<'g, T> Shared<'g, T> {
     pub fn with_tag(&self, tag: usize) -> Shared<'g, T> {
         unsafe { Self::from_usize(data_with_tag::<T>(self.data, tag)) }
     }
+
+    /// Returns the high tag stored within the pointer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::Shared;
+    ///
+    /// assert_eq!(Shared::<u8>::null().high_tag(), 0);
+    /// ```
+    pub fn high_tag(&self) -> usize {
+        high_tag_of(self.data)
+    }
+
+    /// Returns the same pointer, but with the high tag `tag`. `tag` is truncated to its lowest
+    /// `HIGH_TAG_BITS` bits. The tag is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, Atomic};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let a = Atomic::new(0u8);
+    /// let guard = &epoch::pin();
+    /// let p1 = a.load(SeqCst, guard);
+    /// let p2 = p1.with_high_tag(0x81);
+    ///
+    /// if cfg!(any(target_arch = "x86_64", target_arch = "aarch64")) {
+    ///     assert_eq!(p2.high_tag(), 0x81);
+    /// }
+    /// assert_eq!(p2.tag(), 0);
+    /// assert_eq!(p1.as_raw(), p2.as_raw());
+    /// ```
+    pub fn with_high_tag(&self, tag: usize) -> Shared<'g, T> {
+        unsafe { Self::from_usize(data_with_high_tag(self.data, tag)) }
+    }
+
+    /// Returns the address of the allocation, without the tag and the high tag.
+    ///
+    /// For slices, this is the address of the header rather than of the elements.
+    pub(crate) fn address(&self) -> usize {
+        let (raw, _) = decompose_data::<T>(self.data);
+        raw
+    }
 }
 
 impl<'g, T> From<*const T> for Shared<'g, T> {
@@ -1060,49 +1611,53 @@ impl<'g, T> From<*const T> for Shared<'g, T> {
     /// assert!(!p.is_null());
     /// ```
     fn from(raw: *const T) -> Self {
-        ensure_aligned(raw);
+        ensure_aligned::<T>(raw as usize);
+        #[cfg(feature = "debug-reclaim")]
+        ::debug::allocated(raw as usize);
         unsafe { Self::from_usize(raw as usize) }
     }
 }
 
-impl<'g, T> PartialEq<Shared<'g, T>> for Shared<'g, T> {
+impl<'g, T: ?Sized + Pointable> PartialEq<Shared<'g, T>> for Shared<'g, T> {
     fn eq(&self, other: &Self) -> bool {
         self.data == other.data
     }
 }
 
-impl<'g, T> Eq for Shared<'g, T> {}
+impl<'g, T: ?Sized + Pointable> Eq for Shared<'g, T> {}
 
-impl<'g, T> PartialOrd<Shared<'g, T>> for Shared<'g, T> {
+impl<'g, T: ?Sized + Pointable> PartialOrd<Shared<'g, T>> for Shared<'g, T> {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
         self.data.partial_cmp(&other.data)
     }
 }
 
-impl<'g, T> Ord for Shared<'g, T> {
+impl<'g, T: ?Sized + Pointable> Ord for Shared<'g, T> {
     fn cmp(&self, other: &Self) -> cmp::Ordering {
         self.data.cmp(&other.data)
     }
 }
 
-impl<'g, T> fmt::Debug for Shared<'g, T> {
+impl<'g, T: ?Sized + Pointable> fmt::Debug for Shared<'g, T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let (raw, tag) = decompose_data::<T>(self.data);
 
         f.debug_struct("Shared")
-            .field("raw", &raw)
+            .field("raw", &(raw as *const ()))
             .field("tag", &tag)
+            .field("high_tag", &high_tag_of(self.data))
             .finish()
     }
 }
 
-impl<'g, T> fmt::Pointer for Shared<'g, T> {
+impl<'g, T: ?Sized + Pointable> fmt::Pointer for Shared<'g, T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Pointer::fmt(&self.as_raw(), f)
+        let (raw, _) = decompose_data::<T>(self.data);
+        fmt::Pointer::fmt(&(raw as *const ()), f)
     }
 }
 
-impl<'g, T> Default for Shared<'g, T> {
+impl<'g, T: ?Sized + Pointable> Default for Shared<'g, T> {
     fn default() -> Self {
         Shared::null()
     }
@@ -1110,7 +1665,13 @@ impl<'g, T> Default for Shared<'g, T> {
 
 #[cfg(test)]
 mod tests {
-    use super::Shared;
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    use std::sync::atomic::Ordering::{Relaxed, SeqCst};
+
+    use crossbeam_utils::thread;
+
+    use {pin, unprotected, Collector};
+    use super::{Atomic, Owned, Shared};
 
     #[test]
     fn valid_tag_i8() {
@@ -1121,4 +1682,185 @@ mod tests {
     fn valid_tag_i64() {
         Shared::<i64>::null().with_tag(7);
     }
+
+    #[test]
+    fn slice() {
+        let mut o = Owned::<[u64]>::init(vec![1, 2, 3]);
+        o[1] = 5;
+        assert_eq!(*o, [1, 5, 3]);
+
+        let o = o.with_tag(7);
+        assert_eq!(o.tag(), 7);
+        assert_eq!(*o, [1, 5, 3]);
+
+        let a = Atomic::<[u64]>::from(o);
+        unsafe {
+            let p = a.load(SeqCst, unprotected());
+            assert_eq!(p.tag(), 7);
+            assert_eq!(p.deref(), &[1, 5, 3]);
+            drop(p.into_owned());
+        }
+
+        let empty = Owned::<[u64]>::init(Vec::new());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn slice_defer_destroy() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem;
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Relaxed);
+            }
+        }
+
+        let collector = Collector::new();
+        let handle = collector.register();
+
+        let a = Atomic::<[Elem]>::init(vec![Elem, Elem, Elem]);
+        {
+            let guard = &handle.pin();
+            let p = a.swap(Shared::null(), SeqCst, guard);
+            assert_eq!(unsafe { p.deref() }.len(), 3);
+            unsafe {
+                guard.defer_destroy(p);
+            }
+            assert_eq!(DROPS.load(Relaxed), 0);
+        }
+
+        drop(handle);
+        drop(collector);
+        assert_eq!(DROPS.load(Relaxed), 3);
+    }
+
+    #[test]
+    fn tag_bits() {
+        fn check<T>(value: T) {
+            let max = (1usize << Shared::<T>::TAG_BITS) - 1;
+            let o = Owned::new(value);
+            let raw = &*o as *const T;
+
+            let o = o.with_tag(max);
+            assert_eq!(o.tag(), max);
+            let a = Atomic::<T>::from(o);
+            unsafe {
+                let p = a.load(SeqCst, unprotected());
+                assert_eq!(p.as_raw(), raw);
+                assert_eq!(p.tag(), max);
+                assert_eq!(p.with_tag(max + 1).tag(), 0);
+
+                assert_eq!(a.fetch_and(max - 1, SeqCst, unprotected()).tag(), max);
+                assert_eq!(a.fetch_xor(max, SeqCst, unprotected()).tag(), max - 1);
+                assert_eq!(a.fetch_or(max, SeqCst, unprotected()).tag(), 1);
+                let p = a.load(SeqCst, unprotected());
+                assert_eq!(p.as_raw(), raw);
+                assert_eq!(p.tag(), max);
+                assert_eq!(p.high_tag(), 0);
+                drop(p.into_owned());
+            }
+        }
+
+        assert_eq!(Shared::<u64>::TAG_BITS, Shared::<u8>::TAG_BITS + 3);
+        assert_eq!(Atomic::<u32>::TAG_BITS, Owned::<u32>::TAG_BITS);
+        check(0u16);
+        check(0u64);
+        check(vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn high_tag_bits() {
+        if cfg!(target_arch = "x86_64") {
+            assert_eq!(Shared::<u8>::HIGH_TAG_BITS, 16);
+        } else if cfg!(target_arch = "aarch64") {
+            assert_eq!(Shared::<u8>::HIGH_TAG_BITS, 8);
+        } else {
+            assert_eq!(Shared::<u8>::HIGH_TAG_BITS, 0);
+        }
+        assert_eq!(Atomic::<u64>::HIGH_TAG_BITS, Owned::<u32>::HIGH_TAG_BITS);
+
+        let max = (1usize << Shared::<u64>::HIGH_TAG_BITS) - 1;
+        let a = Atomic::<u64>::from(Owned::new(7u64).with_high_tag(max).with_tag(5));
+        unsafe {
+            let guard = unprotected();
+            let p = a.load(SeqCst, guard);
+            assert_eq!(p.high_tag(), max);
+            assert_eq!(p.tag(), 5);
+            assert_eq!(*p.deref(), 7);
+
+            // The tag and the high tag are independent of each other.
+            a.fetch_xor(5, SeqCst, guard);
+            let p = a.load(SeqCst, guard);
+            assert_eq!(p.tag(), 0);
+            assert_eq!(p.high_tag(), max);
+            assert_eq!(p.with_high_tag(0).tag(), 0);
+            assert_eq!(p.with_high_tag(max + 1).high_tag(), 0);
+            assert_eq!(p.with_high_tag(0).as_raw(), p.as_raw());
+            drop(p.into_owned());
+        }
+    }
+
+    #[test]
+    fn fetch_update() {
+        let a = Atomic::new(0);
+        let guard = &pin();
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| for _ in 0..1000 {
+                    let guard = &pin();
+                    let mut allocations = 0;
+                    let res = a.fetch_update(SeqCst, guard, |p, rejected| {
+                        let mut new = rejected.unwrap_or_else(|| {
+                            allocations += 1;
+                            Owned::new(0)
+                        });
+                        *new = unsafe { p.deref() } + 1;
+                        Some(new)
+                    });
+                    assert_eq!(allocations, 1);
+                    let (prev, new) = res.unwrap();
+                    unsafe {
+                        assert_eq!(prev.deref() + 1, *new.deref());
+                        guard.defer_destroy(prev);
+                    }
+                });
+            }
+        });
+
+        let p = a.load(SeqCst, guard);
+        assert_eq!(unsafe { p.deref() }, &4000);
+        let res = a.fetch_update(SeqCst, guard, |_, _| None::<Shared<_>>);
+        assert_eq!(res, Err(p));
+        unsafe { drop(p.into_owned()) }
+    }
+
+    #[test]
+    fn fetch_update_owned() {
+        let a = Atomic::new(0);
+        let guard = &pin();
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| for _ in 0..1000 {
+                    let guard = &pin();
+                    let res = a.fetch_update_owned(Owned::new(0), SeqCst, guard, |p, n| {
+                        *n = unsafe { p.deref() } + 1;
+                        true
+                    });
+                    let (prev, _) = res.unwrap();
+                    unsafe { guard.defer_destroy(prev) }
+                });
+            }
+        });
+
+        let p = a.load(SeqCst, guard);
+        assert_eq!(unsafe { p.deref() }, &4000);
+
+        let res = a.fetch_update_owned(Owned::new(7), SeqCst, guard, |_, _| false);
+        assert_eq!(*res.unwrap_err(), 7);
+        unsafe { drop(p.into_owned()) }
+    }
 }
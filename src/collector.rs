@@ -13,11 +13,26 @@
 /// handle.pin().flush();
 /// ```
 
-use alloc::sync::Arc;
+#[cfg(feature = "use_std")]
+use alloc::vec::Vec;
 use core::fmt;
+use core::sync::atomic::Ordering;
+#[cfg(feature = "use_std")]
+use std::error;
+#[cfg(feature = "use_std")]
+use std::time::Duration;
 
-use internal::{Global, Local};
+use internal::{Config, Global, Local};
 use guard::Guard;
+use limit::{GarbageLimit, GarbagePolicy};
+use membarrier;
+use primitive::sync::Arc;
+use qsbr::QsbrHandle;
+#[cfg(feature = "use_std")]
+use reclaimer::Reclaimer;
+#[cfg(feature = "use_std")]
+use stall::StalledParticipant;
+use stats::CollectorStats;
 
 /// An epoch-based garbage collector.
 pub struct Collector {
@@ -29,14 +44,281 @@ unsafe impl Sync for Collector {}
 
 impl Collector {
     /// Creates a new collector.
+    ///
+    /// The collector is configured with the same parameters as the default collector. Use
+    /// [`CollectorBuilder`] in order to tune them.
+    ///
+    /// [`CollectorBuilder`]: struct.CollectorBuilder.html
     pub fn new() -> Self {
-        Collector { global: Arc::new(Global::new()) }
+        CollectorBuilder::new().build()
     }
 
     /// Registers a new handle for the collector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the collector has a fixed [participant capacity] and all participants are
+    /// registered. Use [`try_register`] to handle this case.
+    ///
+    /// [participant capacity]: struct.CollectorBuilder.html#method.participant_capacity
+    /// [`try_register`]: struct.Collector.html#method.try_register
     pub fn register(&self) -> LocalHandle {
+        self.try_register().expect("all participants of the collector are registered")
+    }
+
+    /// Attempts to register a new handle for the collector.
+    ///
+    /// This fails only if the collector has a fixed [participant capacity] and all participants
+    /// are registered. Otherwise, it is equivalent to [`register`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::CollectorBuilder;
+    ///
+    /// let collector = CollectorBuilder::new().participant_capacity(1).build();
+    ///
+    /// let handle = collector.try_register().unwrap();
+    /// assert!(collector.try_register().is_err());
+    ///
+    /// drop(handle);
+    /// assert!(collector.try_register().is_ok());
+    /// ```
+    ///
+    /// [participant capacity]: struct.CollectorBuilder.html#method.participant_capacity
+    /// [`register`]: struct.Collector.html#method.register
+    pub fn try_register(&self) -> Result<LocalHandle, TryRegisterError> {
         Local::register(self)
     }
+
+    /// Registers a new participant using quiescent-state-based reclamation.
+    ///
+    /// See [`QsbrHandle`] for details.
+    ///
+    /// [`QsbrHandle`]: struct.QsbrHandle.html
+    pub fn register_qsbr(&self) -> QsbrHandle {
+        QsbrHandle::register(self)
+    }
+
+    /// Returns `true` if pinning uses asymmetric fences based on the Linux `membarrier` system
+    /// call.
+    ///
+    /// See [`CollectorBuilder::membarrier`] for details.
+    ///
+    /// [`CollectorBuilder::membarrier`]: struct.CollectorBuilder.html#method.membarrier
+    pub fn uses_membarrier(&self) -> bool {
+        self.global.config.membarrier
+    }
+
+    /// Returns a snapshot of the garbage collection statistics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::Collector;
+    ///
+    /// let collector = Collector::new();
+    /// let handle = collector.register();
+    /// assert_eq!(collector.stats().participants(), 1);
+    ///
+    /// drop(handle);
+    /// assert_eq!(collector.stats().participants(), 0);
+    /// ```
+    pub fn stats(&self) -> CollectorStats {
+        self.global.counters.snapshot()
+    }
+
+    /// Blocks until every participant that was pinned at the time of the call gets unpinned.
+    ///
+    /// When this method returns, every object that was unlinked from a data structure before the
+    /// call can no longer be referenced by any participant, so it is safe to destroy it
+    /// immediately. Functions deferred before the call are not executed by this method, but they
+    /// become eligible for execution.
+    ///
+    /// This method registers a temporary handle for the duration of the call. If the current
+    /// thread already has a handle, prefer [`LocalHandle::synchronize`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread is pinned through a handle to this collector.
+    ///
+    /// Without the `use_std` feature, pinning through other handles can't be detected, and this
+    /// method never returns if the current thread is pinned through one of them.
+    ///
+    /// Panics if the collector has a fixed participant capacity and all participants are
+    /// registered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::Collector;
+    ///
+    /// let collector = Collector::new();
+    /// collector.synchronize();
+    /// ```
+    ///
+    /// [`LocalHandle::synchronize`]: struct.LocalHandle.html#method.synchronize
+    pub fn synchronize(&self) {
+        #[cfg(feature = "use_std")]
+        {
+            if self == ::default::default_collector() {
+                return ::default::synchronize();
+            }
+        }
+
+        self.register().synchronize();
+    }
+
+    /// Waits until all participants unregister, and then executes every pending deferred function.
+    ///
+    /// Participants unregister once their [`LocalHandle`] and all its guards are dropped, at which
+    /// point their thread-local garbage is moved into the global queue. Once there are no
+    /// participants left, this method waits for the global epoch to advance so that all garbage
+    /// becomes safe to destroy, and executes it.
+    ///
+    /// Returns the number of executed deferred functions. This includes the garbage produced by the
+    /// collector itself while collecting, so build the collector with
+    /// [`CollectorBuilder::collect_on_pin`] disabled to get an exact count of deferred functions.
+    ///
+    /// Participants registered while this method is running delay it, and the garbage they defer
+    /// is destroyed only when it is safe, like with any other collection.
+    ///
+    /// If the current thread holds a handle to this collector, this method never returns. In
+    /// particular, it never returns for the default collector if the current thread has pinned it.
+    ///
+    /// A [`Reclaimer`] keeps a participant registered until it is stopped, so every reclaimer of
+    /// this collector must be stopped before calling this method, or it never returns.
+    ///
+    /// Waiting for participants to unregister is a busy-wait: the thread repeatedly yields to the
+    /// scheduler, or spins without yielding if the `use_std` feature is disabled. Avoid calling
+    /// this method while participants are expected to stay registered for long.
+    ///
+    /// This method registers a temporary handle once there are no participants left. If the
+    /// collector has a fixed participant capacity and new participants take all of it first, this
+    /// method waits for one of them to unregister.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::CollectorBuilder;
+    /// use std::sync::mpsc;
+    /// use std::thread;
+    ///
+    /// let collector = CollectorBuilder::new().collect_on_pin(false).build();
+    /// let (registered, rx) = mpsc::channel();
+    ///
+    /// let c = collector.clone();
+    /// thread::spawn(move || {
+    ///     let handle = c.register();
+    ///     registered.send(()).unwrap();
+    ///
+    ///     for _ in 0..10 {
+    ///         handle.pin().defer(|| ());
+    ///     }
+    /// });
+    ///
+    /// // Waits for the thread to register. `shutdown` then waits for it to drop its handle.
+    /// rx.recv().unwrap();
+    /// assert_eq!(collector.shutdown(), 10);
+    /// ```
+    ///
+    /// [`LocalHandle`]: struct.LocalHandle.html
+    /// [`CollectorBuilder::collect_on_pin`]: struct.CollectorBuilder.html#method.collect_on_pin
+    /// [`Reclaimer`]: struct.Reclaimer.html
+    pub fn shutdown(self) -> usize {
+        let handle = loop {
+            if self.global.counters.participants.load(Ordering::Acquire) == 0 {
+                if let Ok(handle) = self.try_register() {
+                    break handle;
+                }
+            }
+
+            #[cfg(feature = "use_std")]
+            ::primitive::thread::yield_now();
+            #[cfg(not(feature = "use_std"))]
+            #[allow(deprecated)]
+            ::core::sync::atomic::spin_loop_hint();
+        };
+        handle.synchronize();
+        self.global.collect_expired(&handle.pin())
+    }
+
+    /// Spawns a background thread that collects garbage every `interval`.
+    ///
+    /// On each wakeup the thread advances the global epoch and executes all deferred functions
+    /// that have become safe to execute. The thread is stopped when the returned [`Reclaimer`] is
+    /// dropped.
+    ///
+    /// By default, threads that pin and flush keep collecting garbage as well. Build the collector
+    /// with [`CollectorBuilder::collect_on_pin`] disabled so that deferred functions are executed
+    /// only by the reclaimer thread.
+    ///
+    /// The reclaimer thread is a registered participant, so [`shutdown`] waits for it to be
+    /// stopped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread cannot be spawned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::CollectorBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let collector = CollectorBuilder::new().collect_on_pin(false).build();
+    /// let reclaimer = collector.spawn_reclaimer(Duration::from_millis(10));
+    ///
+    /// let handle = collector.register();
+    /// handle.pin().defer(|| println!("executed on the reclaimer thread"));
+    ///
+    /// drop(reclaimer);
+    /// ```
+    ///
+    /// [`Reclaimer`]: struct.Reclaimer.html
+    /// [`CollectorBuilder::collect_on_pin`]: struct.CollectorBuilder.html#method.collect_on_pin
+    /// [`shutdown`]: struct.Collector.html#method.shutdown
+    #[cfg(feature = "use_std")]
+    pub fn spawn_reclaimer(&self, interval: Duration) -> Reclaimer {
+        Reclaimer::spawn(self, interval)
+    }
+
+    /// Returns the participants that have been preventing the global epoch from advancing for at
+    /// least `threshold`.
+    ///
+    /// A participant is stalled if it is pinned in an epoch older than the global epoch. Stalls
+    /// are timed from the moment they are first observed, either by this method or by a thread
+    /// attempting to advance the global epoch, so the first call may report a stall that started
+    /// earlier as lasting zero time.
+    ///
+    /// This method registers a temporary handle for the duration of the call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the collector has a fixed participant capacity and all participants are
+    /// registered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::Collector;
+    /// use std::time::Duration;
+    ///
+    /// let collector = Collector::new();
+    /// for participant in collector.stalled_participants(Duration::from_secs(1)) {
+    ///     eprintln!(
+    ///         "thread {:?} has been pinned for over {:?}",
+    ///         participant.thread_name(),
+    ///         participant.blocking_for()
+    ///     );
+    /// }
+    /// ```
+    #[cfg(feature = "use_std")]
+    pub fn stalled_participants(&self, threshold: Duration) -> Vec<StalledParticipant> {
+        let handle = self.register();
+        let guard = &handle.pin();
+        self.global.stalled_participants(threshold, guard)
+    }
 }
 
 impl Clone for Collector {
@@ -60,6 +342,274 @@ impl PartialEq for Collector {
 }
 impl Eq for Collector {}
 
+/// A builder for garbage collectors with custom parameters.
+///
+/// Every parameter defaults to the value used by the default collector.
+///
+/// # Examples
+///
+/// ```
+/// use crossbeam_epoch::CollectorBuilder;
+///
+/// let collector = CollectorBuilder::new()
+///     .bag_capacity(16)
+///     .collect_steps(2)
+///     .pinnings_between_collect(32)
+///     .build();
+///
+/// let handle = collector.register();
+/// handle.pin().flush();
+/// ```
+#[derive(Debug)]
+pub struct CollectorBuilder {
+    config: Config,
+
+    /// Whether to use `membarrier` if it is supported.
+    membarrier: bool,
+}
+
+impl CollectorBuilder {
+    /// Creates a new builder with default parameters.
+    pub fn new() -> Self {
+        CollectorBuilder {
+            config: Config::default(),
+            membarrier: cfg!(feature = "membarrier"),
+        }
+    }
+
+    /// Sets the maximum number of deferred functions a thread-local bag can contain.
+    ///
+    /// Once a bag is full, it is sealed and moved into the global queue. Smaller bags make garbage
+    /// available for collection sooner, while larger bags amortize the cost of pushing into the
+    /// global queue over more objects.
+    ///
+    /// The default is 64.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn bag_capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "bag capacity must be greater than zero");
+        self.config.bag_capacity = capacity;
+        self
+    }
+
+    /// Sets the maximum number of sealed bags destroyed in a single collection.
+    ///
+    /// Collection happens on the thread that triggers it, so this bounds the number of bags a
+    /// single pinning or flush may destroy. It doesn't bound the time spent in destructors, since
+    /// each bag holds up to `bag_capacity` deferred functions and any of them may run for
+    /// arbitrarily long. Use `collect_on_pin` and a background reclaimer to move destructor work
+    /// off the threads that pin.
+    ///
+    /// The default is 8.
+    pub fn collect_steps(mut self, steps: usize) -> Self {
+        self.config.collect_steps = steps;
+        self
+    }
+
+    /// Sets the number of pinnings after which a participant attempts to advance the global epoch
+    /// and collect some garbage.
+    ///
+    /// The default is 128.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pinnings` is zero.
+    pub fn pinnings_between_collect(mut self, pinnings: usize) -> Self {
+        assert!(pinnings > 0, "number of pinnings between collections must be greater than zero");
+        self.config.pinnings_between_collect = pinnings;
+        self
+    }
+
+    /// Sets whether pinning and flushing collect garbage.
+    ///
+    /// If disabled, pinning never executes deferred functions and flushing only moves the
+    /// thread-local bag into the global queue. Garbage is then collected only by a background
+    /// thread spawned with [`Collector::spawn_reclaimer`], or when the collector is destroyed.
+    ///
+    /// The default is `true`.
+    ///
+    /// [`Collector::spawn_reclaimer`]: struct.Collector.html#method.spawn_reclaimer
+    pub fn collect_on_pin(mut self, collect: bool) -> Self {
+        self.config.collect_on_pin = collect;
+        self
+    }
+
+    /// Sets a limit on the amount of garbage waiting in the global queue.
+    ///
+    /// While the limit is exceeded, deferring a function applies the policy set with
+    /// [`garbage_policy`], and [`Guard::try_defer`] fails.
+    ///
+    /// By default, there is no limit.
+    ///
+    /// [`garbage_policy`]: struct.CollectorBuilder.html#method.garbage_policy
+    /// [`Guard::try_defer`]: struct.Guard.html#method.try_defer
+    pub fn garbage_limit(mut self, limit: GarbageLimit) -> Self {
+        self.config.garbage_limit = Some(limit);
+        self
+    }
+
+    /// Sets what happens when a thread defers a function while the garbage limit is exceeded.
+    ///
+    /// Has no effect unless a limit is set with [`garbage_limit`].
+    ///
+    /// The default is [`GarbagePolicy::Block`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{CollectorBuilder, CollectorStats, GarbageLimit, GarbagePolicy};
+    ///
+    /// fn report(stats: &CollectorStats) {
+    ///     eprintln!("{} bytes of garbage are waiting", stats.queued_bytes());
+    /// }
+    ///
+    /// let collector = CollectorBuilder::new()
+    ///     .garbage_limit(GarbageLimit::Bytes(1 << 20))
+    ///     .garbage_policy(GarbagePolicy::Callback(report))
+    ///     .build();
+    /// ```
+    ///
+    /// [`garbage_limit`]: struct.CollectorBuilder.html#method.garbage_limit
+    /// [`GarbagePolicy::Block`]: enum.GarbagePolicy.html#variant.Block
+    pub fn garbage_policy(mut self, policy: GarbagePolicy) -> Self {
+        self.config.garbage_policy = policy;
+        self
+    }
+
+    /// Sets a function called once a participant has been preventing the global epoch from
+    /// advancing for at least `threshold`.
+    ///
+    /// Stalls are observed by threads attempting to advance the global epoch, so the function is
+    /// called from one of them, at most once per stall. Keep it short: garbage collection waits
+    /// for it to return.
+    ///
+    /// By default, no function is called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{CollectorBuilder, StalledParticipant};
+    /// use std::time::Duration;
+    ///
+    /// fn report(participant: &StalledParticipant) {
+    ///     eprintln!(
+    ///         "thread {:?} is holding a guard for too long",
+    ///         participant.thread_name()
+    ///     );
+    /// }
+    ///
+    /// let collector = CollectorBuilder::new()
+    ///     .stall_callback(Duration::from_secs(1), report)
+    ///     .build();
+    /// ```
+    #[cfg(feature = "use_std")]
+    pub fn stall_callback(
+        mut self,
+        threshold: Duration,
+        callback: fn(&StalledParticipant),
+    ) -> Self {
+        self.config.stall_callback = Some((threshold, callback));
+        self
+    }
+
+    /// Sets whether pinning uses asymmetric fences based on the Linux `membarrier` system call.
+    ///
+    /// Pinning normally executes a `SeqCst` fence, which dominates its cost. With this option,
+    /// pinning only prevents compiler reordering, and advancing the global epoch issues
+    /// `membarrier(MEMBARRIER_CMD_PRIVATE_EXPEDITED)` instead, which executes a full fence on all
+    /// threads of the process. Advancing the global epoch becomes considerably more expensive, so
+    /// this pays off when pinning is much more frequent than collection.
+    ///
+    /// If the system call is unavailable, e.g. on other platforms, kernels older than Linux 4.14,
+    /// or without the `membarrier` feature, the collector silently falls back to regular fences.
+    /// Use [`Collector::uses_membarrier`] to check which mode is in use.
+    ///
+    /// The default is `true` with the `membarrier` feature, and `false` otherwise.
+    ///
+    /// [`Collector::uses_membarrier`]: struct.Collector.html#method.uses_membarrier
+    pub fn membarrier(mut self, enable: bool) -> Self {
+        self.membarrier = enable;
+        self
+    }
+
+    /// Preallocates a fixed number of participants instead of allocating one per registration.
+    ///
+    /// Participants are normally kept in a lock-free linked list, which threads attempting to
+    /// advance the global epoch traverse one node at a time, restarting whenever a concurrent
+    /// update gets in the way. With a fixed capacity, they are kept in an array of cache-padded
+    /// slots instead, which is scanned linearly and never restarts. In return, at most `capacity`
+    /// handles can be registered at the same time: beyond that, [`Collector::try_register`] fails
+    /// and [`Collector::register`] panics.
+    ///
+    /// The collector itself may register a temporary handle, e.g. in [`Collector::shutdown`], so
+    /// leave room for it if needed.
+    ///
+    /// By default, there is no limit on the number of participants.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    ///
+    /// [`Collector::try_register`]: struct.Collector.html#method.try_register
+    /// [`Collector::register`]: struct.Collector.html#method.register
+    /// [`Collector::shutdown`]: struct.Collector.html#method.shutdown
+    pub fn participant_capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "participant capacity must be greater than zero");
+        self.config.participant_capacity = Some(capacity);
+        self
+    }
+
+    /// Creates a new collector with the configured parameters.
+    pub fn build(mut self) -> Collector {
+        self.config.membarrier = self.membarrier && membarrier::is_supported();
+        Collector { global: Arc::new(Global::new(self.config)) }
+    }
+}
+
+impl Default for CollectorBuilder {
+    fn default() -> Self {
+        CollectorBuilder::new()
+    }
+}
+
+/// The error returned by [`Collector::try_register`] when all participants are registered.
+///
+/// This can only happen if the collector was built with a fixed [participant capacity].
+///
+/// [`Collector::try_register`]: struct.Collector.html#method.try_register
+/// [participant capacity]: struct.CollectorBuilder.html#method.participant_capacity
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TryRegisterError {
+    _private: (),
+}
+
+impl TryRegisterError {
+    pub(crate) fn new() -> Self {
+        TryRegisterError { _private: () }
+    }
+}
+
+impl fmt::Debug for TryRegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TryRegisterError").finish()
+    }
+}
+
+impl fmt::Display for TryRegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "all participants are registered")
+    }
+}
+
+#[cfg(feature = "use_std")]
+impl error::Error for TryRegisterError {
+    fn description(&self) -> &str {
+        "all participants are registered"
+    }
+}
+
 /// A handle to a garbage collector.
 pub struct LocalHandle {
     pub(crate) local: *const Local,
@@ -78,6 +628,30 @@ impl LocalHandle {
         unsafe { (*self.local).is_pinned() }
     }
 
+    /// Blocks until every participant that was pinned at the time of the call gets unpinned.
+    ///
+    /// See [`Collector::synchronize`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the handle or another handle of the current thread to the same collector is
+    /// pinned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::Collector;
+    ///
+    /// let collector = Collector::new();
+    /// let handle = collector.register();
+    /// handle.synchronize();
+    /// ```
+    ///
+    /// [`Collector::synchronize`]: struct.Collector.html#method.synchronize
+    pub fn synchronize(&self) {
+        unsafe { (*self.local).synchronize() }
+    }
+
     /// Returns the `Collector` associated with this handle.
     #[inline]
     pub fn collector(&self) -> &Collector {
@@ -103,12 +677,16 @@ impl fmt::Debug for LocalHandle {
 #[cfg(test)]
 mod tests {
     use std::mem;
-    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    use std::sync::Barrier;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, ATOMIC_USIZE_INIT};
     use std::sync::atomic::Ordering;
+    use std::thread::sleep;
+    use std::time::Duration;
 
     use crossbeam_utils::thread;
 
-    use {Collector, Owned};
+    use {Collector, CollectorBuilder, CollectorStats, GarbageLimit, GarbagePolicy, Owned};
+    use StalledParticipant;
 
     const NUM_THREADS: usize = 8;
 
@@ -143,9 +721,9 @@ mod tests {
                 let a = Owned::new(7).into_shared(guard);
                 guard.defer_destroy(a);
 
-                assert!(!(*(*guard.local).bag.get()).is_empty());
+                assert!(!(*guard.local).bag.with(|b| (*b).is_empty()));
 
-                while !(*(*guard.local).bag.get()).is_empty() {
+                while !(*guard.local).bag.with(|b| (*b).is_empty()) {
                     guard.flush();
                 }
             }
@@ -164,8 +742,468 @@ mod tests {
                 let a = Owned::new(7).into_shared(guard);
                 guard.defer_destroy(a);
             }
-            assert!(!(*(*guard.local).bag.get()).is_empty());
+            assert!(!(*guard.local).bag.with(|b| (*b).is_empty()));
+        }
+    }
+
+    #[test]
+    fn defer_unprotected() {
+        static CALLS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        unsafe {
+            let guard = ::unprotected();
+            guard.defer(|| CALLS.fetch_add(1, Ordering::Relaxed));
+            guard.defer_unchecked(|| CALLS.fetch_add(1, Ordering::Relaxed));
+            assert!(guard.try_defer(|| CALLS.fetch_add(1, Ordering::Relaxed)).is_ok());
+        }
+        assert_eq!(CALLS.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn custom_bag_capacity() {
+        static DESTROYS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        let collector = CollectorBuilder::new().bag_capacity(3).build();
+        let handle = collector.register();
+
+        unsafe {
+            let guard = &handle.pin();
+            for _ in 0..4 {
+                guard.defer_unchecked(move || {
+                    DESTROYS.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+        }
+
+        // Only the first three functions made it into a sealed bag.
+        for _ in 0..100 {
+            collector.global.collect(&handle.pin());
+        }
+        assert_eq!(DESTROYS.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn custom_collect_steps() {
+        const COUNT: usize = 1000;
+        static DESTROYS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        let collector = CollectorBuilder::new()
+            .bag_capacity(4)
+            .collect_steps(1)
+            .build();
+        let handle = collector.register();
+
+        unsafe {
+            let guard = &handle.pin();
+            for _ in 0..COUNT {
+                guard.defer_unchecked(move || {
+                    DESTROYS.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+            guard.flush();
+        }
+
+        let mut last = 0;
+
+        while last < COUNT {
+            let curr = DESTROYS.load(Ordering::Relaxed);
+            assert!(curr - last <= 8);
+            last = curr;
+
+            let guard = &handle.pin();
+            collector.global.collect(guard);
+        }
+        assert_eq!(DESTROYS.load(Ordering::Relaxed), COUNT);
+    }
+
+    #[test]
+    fn no_collect_on_pin() {
+        static DESTROYS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        let collector = CollectorBuilder::new().collect_on_pin(false).build();
+        let handle = collector.register();
+
+        for _ in 0..1000 {
+            let guard = &handle.pin();
+            guard.defer(move || {
+                DESTROYS.fetch_add(1, Ordering::Relaxed);
+            });
+            guard.flush();
+        }
+        assert_eq!(DESTROYS.load(Ordering::Relaxed), 0);
+
+        while DESTROYS.load(Ordering::Relaxed) < 1000 {
+            collector.global.collect(&handle.pin());
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let collector = CollectorBuilder::new().bag_capacity(4).build();
+        let handle = collector.register();
+        assert_eq!(collector.stats().participants(), 1);
+
+        {
+            let guard = &handle.pin();
+            for _ in 0..10 {
+                guard.defer(move || ());
+            }
+        }
+        let stats = collector.stats();
+        assert_eq!(stats.sealed_bags(), 2);
+        assert_eq!(stats.queued_bags(), 2);
+        assert_eq!(stats.queued_deferreds(), 8);
+
+        while collector.stats().queued_bags() > 0 {
+            collector.global.collect(&handle.pin());
+        }
+        let stats = collector.stats();
+        assert_eq!(stats.collected_bags(), 2);
+        assert_eq!(stats.queued_deferreds(), 0);
+        assert!(stats.epoch_advances() >= 2);
+
+        let other = collector.register();
+        {
+            let _guard = &other.pin();
+            collector.global.collect(&handle.pin());
+            collector.global.collect(&handle.pin());
+        }
+        assert!(collector.stats().blocked_advances() > 0);
+        assert_eq!(collector.stats().participants(), 2);
+
+        drop(other);
+        assert_eq!(collector.stats().participants(), 1);
+    }
+
+    #[test]
+    fn synchronize_waits_for_pinned() {
+        let collector = Collector::new();
+        let pinned = AtomicBool::new(false);
+        let unpinned = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let handle = collector.register();
+                let guard = handle.pin();
+                pinned.store(true, Ordering::SeqCst);
+
+                sleep(Duration::from_millis(100));
+                unpinned.store(true, Ordering::SeqCst);
+                drop(guard);
+            });
+
+            while !pinned.load(Ordering::SeqCst) {}
+            collector.synchronize();
+            assert!(unpinned.load(Ordering::SeqCst));
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn synchronize_while_pinned() {
+        let collector = Collector::new();
+        let handle = collector.register();
+        let _guard = handle.pin();
+        handle.synchronize();
+    }
+
+    #[test]
+    #[should_panic(expected = "the current thread is pinned")]
+    fn synchronize_while_pinned_through_other_handle() {
+        let collector = Collector::new();
+        let handle = collector.register();
+        let _guard = handle.pin();
+        collector.synchronize();
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_bag_capacity() {
+        CollectorBuilder::new().bag_capacity(0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_participant_capacity() {
+        CollectorBuilder::new().participant_capacity(0);
+    }
+
+    #[test]
+    fn participant_capacity() {
+        let collector = CollectorBuilder::new().participant_capacity(2).build();
+
+        let a = collector.register();
+        let b = collector.try_register().unwrap();
+        assert!(collector.try_register().is_err());
+        assert_eq!(collector.stats().participants(), 2);
+
+        drop(a);
+        let c = collector.try_register().unwrap();
+        assert!(collector.try_register().is_err());
+
+        // A handle still pinned by a guard keeps its participant registered.
+        let guard = b.pin();
+        drop(b);
+        assert!(collector.try_register().is_err());
+        drop(guard);
+        assert!(collector.try_register().is_ok());
+
+        drop(c);
+        assert_eq!(collector.stats().participants(), 0);
+    }
+
+    #[test]
+    fn participant_capacity_stress() {
+        const COUNT: usize = 1_000;
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        let collector = CollectorBuilder::new().participant_capacity(2).build();
+
+        thread::scope(|scope| {
+            for _ in 0..NUM_THREADS {
+                scope.spawn(|| {
+                    for _ in 0..COUNT {
+                        let handle = loop {
+                            match collector.try_register() {
+                                Ok(handle) => break handle,
+                                Err(_) => ::std::thread::yield_now(),
+                            }
+                        };
+                        handle.pin().defer(|| DROPS.fetch_add(1, Ordering::Relaxed));
+                    }
+                });
+            }
+        });
+
+        drop(collector);
+        assert_eq!(DROPS.load(Ordering::Relaxed), COUNT * NUM_THREADS);
+    }
+
+    #[test]
+    #[should_panic(expected = "all participants of the collector are registered")]
+    fn synchronize_full_capacity() {
+        let collector = CollectorBuilder::new().participant_capacity(1).build();
+        let _handle = collector.register();
+        collector.synchronize();
+    }
+
+    #[test]
+    fn shutdown_full_capacity() {
+        const COUNT: usize = 1_000;
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        let collector = CollectorBuilder::new().participant_capacity(1).build();
+
+        thread::scope(|scope| {
+            for _ in 0..NUM_THREADS {
+                scope.spawn(|| {
+                    for _ in 0..COUNT {
+                        let handle = loop {
+                            match collector.try_register() {
+                                Ok(handle) => break handle,
+                                Err(_) => ::std::thread::yield_now(),
+                            }
+                        };
+                        handle.pin().defer(|| DROPS.fetch_add(1, Ordering::Relaxed));
+                    }
+                });
+            }
+
+            // Competes with the other threads for the only participant.
+            collector.clone().shutdown();
+        });
+
+        drop(collector);
+        assert_eq!(DROPS.load(Ordering::Relaxed), COUNT * NUM_THREADS);
+    }
+
+    #[test]
+    fn try_defer_over_limit() {
+        let collector = CollectorBuilder::new()
+            .bag_capacity(4)
+            .garbage_limit(GarbageLimit::Deferreds(4))
+            .build();
+        let other = collector.register();
+        let _other_guard = other.pin();
+
+        let handle = collector.register();
+        let guard = &handle.pin();
+        for _ in 0..8 {
+            assert!(guard.try_defer(move || ()).is_ok());
+        }
+        guard.flush();
+        assert_eq!(collector.stats().queued_deferreds(), 8);
+
+        let err = guard.try_defer(move || 7).unwrap_err();
+        assert_eq!((err.into_inner())(), 7);
+    }
+
+    #[test]
+    fn garbage_limit_bytes() {
+        let collector = CollectorBuilder::new()
+            .garbage_limit(GarbageLimit::Bytes(1000))
+            .build();
+        let other = collector.register();
+        let _other_guard = other.pin();
+
+        let handle = collector.register();
+        let guard = &handle.pin();
+        unsafe {
+            let a = Owned::new([0u8; 600]).into_shared(guard);
+            guard.defer_destroy(a);
+        }
+        guard.flush();
+        assert_eq!(collector.stats().queued_bytes(), 600);
+        assert!(guard.try_defer(move || ()).is_ok());
+
+        unsafe {
+            let a = Owned::new([0u8; 600]).into_shared(guard);
+            guard.defer_destroy(a);
+        }
+        guard.flush();
+        assert_eq!(collector.stats().queued_bytes(), 1200);
+        assert!(guard.try_defer(move || ()).is_err());
+    }
+
+    #[test]
+    fn garbage_policy_callback() {
+        static CALLS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        fn callback(stats: &CollectorStats) {
+            assert!(stats.queued_deferreds() > 2);
+            CALLS.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let collector = CollectorBuilder::new()
+            .bag_capacity(2)
+            .garbage_limit(GarbageLimit::Deferreds(2))
+            .garbage_policy(GarbagePolicy::Callback(callback))
+            .build();
+        let other = collector.register();
+        let _other_guard = other.pin();
+
+        let handle = collector.register();
+        let guard = &handle.pin();
+        for _ in 0..5 {
+            guard.defer(move || ());
+        }
+        assert_eq!(CALLS.load(Ordering::Relaxed), 0);
+
+        guard.defer(move || ());
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn garbage_policy_block() {
+        let collector = CollectorBuilder::new()
+            .bag_capacity(2)
+            .garbage_limit(GarbageLimit::Deferreds(2))
+            .garbage_policy(GarbagePolicy::Block)
+            .build();
+        let pinned = AtomicBool::new(false);
+        let unpinned = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let handle = collector.register();
+                let guard = handle.pin();
+                pinned.store(true, Ordering::SeqCst);
+
+                sleep(Duration::from_millis(100));
+                unpinned.store(true, Ordering::SeqCst);
+                drop(guard);
+            });
+
+            while !pinned.load(Ordering::SeqCst) {}
+
+            let handle = collector.register();
+            for _ in 0..5 {
+                handle.pin().defer(move || ());
+            }
+            assert!(!unpinned.load(Ordering::SeqCst));
+
+            // The global queue now holds 4 deferred functions, so this blocks until the other
+            // thread unpins.
+            handle.pin().defer(move || ());
+            assert!(unpinned.load(Ordering::SeqCst));
+        });
+    }
+
+    #[test]
+    fn stalled_participants() {
+        let collector = Collector::new();
+        let barrier = Barrier::new(2);
+
+        thread::scope(|scope| {
+            scope.builder().name("stalled".into()).spawn(|| {
+                let handle = collector.register();
+                let guard = handle.pin();
+                barrier.wait();
+                barrier.wait();
+                drop(guard);
+                barrier.wait();
+            }).unwrap();
+
+            barrier.wait();
+
+            // Make sure the global epoch is advanced past the epoch the other thread is pinned in.
+            collector.register().pin().flush();
+
+            let stalled = collector.stalled_participants(Duration::from_secs(0));
+            assert_eq!(stalled.len(), 1);
+            assert_eq!(stalled[0].thread_name(), Some("stalled"));
+            assert!(stalled[0].thread_id().is_some());
+            assert_eq!(stalled[0].epoch_lag(), 1);
+
+            assert!(collector.stalled_participants(Duration::from_secs(60)).is_empty());
+            sleep(Duration::from_millis(50));
+            let stalled = collector.stalled_participants(Duration::from_millis(50));
+            assert_eq!(stalled.len(), 1);
+            assert!(stalled[0].blocking_for() >= Duration::from_millis(50));
+
+            barrier.wait();
+            barrier.wait();
+            assert!(collector.stalled_participants(Duration::from_secs(0)).is_empty());
+        });
+    }
+
+    #[test]
+    fn stall_callback() {
+        static CALLS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        fn callback(participant: &StalledParticipant) {
+            assert_eq!(participant.thread_name(), Some("stalled"));
+            assert!(participant.blocking_for() >= Duration::from_millis(20));
+            CALLS.fetch_add(1, Ordering::Relaxed);
         }
+
+        let collector = CollectorBuilder::new()
+            .stall_callback(Duration::from_millis(20), callback)
+            .build();
+        let barrier = Barrier::new(2);
+
+        thread::scope(|scope| {
+            scope.builder().name("stalled".into()).spawn(|| {
+                let handle = collector.register();
+                let guard = handle.pin();
+                barrier.wait();
+                barrier.wait();
+                drop(guard);
+            }).unwrap();
+
+            barrier.wait();
+            let handle = collector.register();
+            handle.pin().flush();
+            handle.pin().flush();
+            assert_eq!(CALLS.load(Ordering::Relaxed), 0);
+
+            sleep(Duration::from_millis(20));
+            for _ in 0..4 {
+                handle.pin().flush();
+            }
+            assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+            barrier.wait();
+        });
     }
 
     #[test]
@@ -190,6 +1228,26 @@ mod tests {
         })
     }
 
+    #[test]
+    fn repin_holds_advance() {
+        let collector = Collector::new();
+        let pinned = collector.register();
+        let other = collector.register();
+
+        let mut guard = pinned.pin();
+        for _ in 0..10 {
+            other.pin().flush();
+        }
+        guard.repin();
+
+        let before = collector.global.epoch.load(Ordering::Relaxed);
+        for _ in 0..10 {
+            other.pin().flush();
+        }
+        let after = collector.global.epoch.load(Ordering::Relaxed);
+        assert!(after.wrapping_sub(before) <= 1);
+    }
+
     #[test]
     fn incremental() {
         const COUNT: usize = 100_000;
@@ -256,6 +1314,108 @@ mod tests {
         assert_eq!(DESTROYS.load(Ordering::Relaxed), COUNT);
     }
 
+    #[test]
+    fn membarrier() {
+        const COUNT: usize = 10_000;
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        let collector = CollectorBuilder::new().build();
+        assert_eq!(
+            collector.uses_membarrier(),
+            cfg!(feature = "membarrier") && ::membarrier::is_supported()
+        );
+
+        let collector = CollectorBuilder::new().membarrier(false).build();
+        assert!(!collector.uses_membarrier());
+
+        let collector = CollectorBuilder::new().membarrier(true).build();
+        assert_eq!(collector.uses_membarrier(), ::membarrier::is_supported());
+
+        thread::scope(|scope| {
+            for _ in 0..NUM_THREADS {
+                scope.spawn(|| {
+                    let handle = collector.register();
+                    for _ in 0..COUNT {
+                        let guard = &handle.pin();
+                        guard.defer(|| DROPS.fetch_add(1, Ordering::Relaxed));
+                    }
+                });
+            }
+        });
+        drop(collector);
+        assert_eq!(DROPS.load(Ordering::Relaxed), COUNT * NUM_THREADS);
+    }
+
+    #[test]
+    fn shutdown() {
+        const COUNT: usize = 1_000;
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        let collector = CollectorBuilder::new().collect_on_pin(false).build();
+        let started = Barrier::new(NUM_THREADS + 1);
+
+        let executed = thread::scope(|scope| {
+            for _ in 0..NUM_THREADS {
+                let collector = collector.clone();
+                let started = &started;
+                scope.spawn(move || {
+                    let handle = collector.register();
+                    started.wait();
+
+                    // Keep the participant registered for a while, so that `shutdown` has to
+                    // wait for it.
+                    sleep(Duration::from_millis(50));
+                    for _ in 0..COUNT {
+                        handle.pin().defer(|| DROPS.fetch_add(1, Ordering::Relaxed));
+                    }
+                });
+            }
+
+            started.wait();
+            collector.clone().shutdown()
+        });
+
+        assert_eq!(executed, COUNT * NUM_THREADS);
+        assert_eq!(DROPS.load(Ordering::Relaxed), COUNT * NUM_THREADS);
+    }
+
+    #[test]
+    fn shutdown_waits_for_reclaimer() {
+        const COUNT: usize = 1_000;
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        let collector = CollectorBuilder::new().collect_on_pin(false).build();
+        let reclaimer = collector.spawn_reclaimer(Duration::from_millis(1));
+        while collector.stats().participants() == 0 {
+            sleep(Duration::from_millis(1));
+        }
+
+        {
+            let handle = collector.register();
+            for _ in 0..COUNT {
+                handle.pin().defer(|| DROPS.fetch_add(1, Ordering::Relaxed));
+            }
+        }
+
+        let done = AtomicBool::new(false);
+        thread::scope(|scope| {
+            let collector = collector.clone();
+            let done = &done;
+            scope.spawn(move || {
+                collector.shutdown();
+                done.store(true, Ordering::SeqCst);
+            });
+
+            // The reclaimer's participant keeps `shutdown` waiting until it is stopped.
+            sleep(Duration::from_millis(50));
+            assert!(!done.load(Ordering::SeqCst));
+            reclaimer.stop();
+        });
+
+        assert!(done.load(Ordering::SeqCst));
+        assert_eq!(DROPS.load(Ordering::Relaxed), COUNT);
+    }
+
     #[test]
     fn count_drops() {
         const COUNT: usize = 100_000;
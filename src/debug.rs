@@ -0,0 +1,237 @@
+//! Detection of reclamation mistakes, enabled by the `debug-reclaim` feature.
+//!
+//! Every object allocated through `Owned` is tracked in a global side table from the moment it is
+//! retired with `defer_destroy` until it is freed:
+//!
+//! * Retiring the same object twice panics.
+//! * Calling `Shared::into_owned` on a retired object panics, since the collector is going to free
+//!   it as well.
+//! * Calling `Shared::into_owned` on an object that was already freed panics.
+//! * The memory of freed objects is overwritten with `POISON` before it is deallocated, so that
+//!   use-after-free bugs are more likely to show up.
+//! * When a collector is dropped, objects that were retired through it but never freed are
+//!   reported on standard error.
+//!
+//! The table is protected by a single mutex, so this is only meant for debugging.
+
+use std::collections::{HashMap, HashSet};
+use std::ptr;
+use std::sync::{Mutex, MutexGuard};
+use std::vec::Vec;
+
+/// The byte freed objects are filled with.
+const POISON: u8 = 0xde;
+
+/// Addresses below this value are dangling pointers to zero-sized values, which are shared by all
+/// such values and hence can't be tracked.
+const MIN_ADDRESS: usize = 4096;
+
+/// The side table of tracked objects.
+struct Table {
+    /// Retired objects that haven't been freed yet, and the collectors they were retired through.
+    retired: HashMap<usize, usize>,
+
+    /// Freed objects whose memory hasn't been reused by another `Owned`, `Shared` or `Atomic` yet.
+    freed: HashSet<usize>,
+}
+
+lazy_static! {
+    static ref TABLE: Mutex<Table> = Mutex::new(Table {
+        retired: HashMap::new(),
+        freed: HashSet::new(),
+    });
+}
+
+/// Locks the table.
+///
+/// The lock is never held while panicking, but tests expecting a panic may still poison it.
+fn table() -> MutexGuard<'static, Table> {
+    TABLE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Records that the object at `ptr` was retired through the collector identified by `owner`.
+///
+/// # Panics
+///
+/// Panics if the object is already retired or was freed.
+pub fn retire(ptr: usize, owner: usize) {
+    if ptr < MIN_ADDRESS {
+        return;
+    }
+
+    let (retired, freed) = {
+        let mut table = table();
+        let freed = table.freed.contains(&ptr);
+        let retired = table.retired.insert(ptr, owner).is_some();
+        (retired, freed)
+    };
+    assert!(!retired, "pointer {:#x} retired twice", ptr);
+    assert!(!freed, "retiring pointer {:#x}, which was already freed", ptr);
+}
+
+/// Records that the collector is about to free the retired object at `ptr`.
+pub fn reclaim(ptr: usize) {
+    if ptr < MIN_ADDRESS {
+        return;
+    }
+
+    table().retired.remove(&ptr);
+}
+
+/// Checks that the object at `ptr` may be converted into an `Owned`.
+///
+/// # Panics
+///
+/// Panics if the object is retired or was freed.
+pub fn into_owned(ptr: usize) {
+    if ptr < MIN_ADDRESS {
+        return;
+    }
+
+    let (retired, freed) = {
+        let table = table();
+        (table.retired.contains_key(&ptr), table.freed.contains(&ptr))
+    };
+    assert!(
+        !retired,
+        "converting pointer {:#x} into `Owned`, but it is retired and will be freed by the \
+         collector",
+        ptr
+    );
+    assert!(!freed, "converting pointer {:#x} into `Owned`, but it was already freed", ptr);
+}
+
+/// Records that an `Owned` now owns the object at `ptr`.
+pub fn allocated(ptr: usize) {
+    if ptr < MIN_ADDRESS {
+        return;
+    }
+
+    table().freed.remove(&ptr);
+}
+
+/// Records that the object at `ptr` is about to be freed.
+pub fn freed(ptr: usize) {
+    if ptr < MIN_ADDRESS {
+        return;
+    }
+
+    table().freed.insert(ptr);
+}
+
+/// Overwrites `size` bytes at `ptr` with `POISON`.
+pub unsafe fn poison(ptr: *mut u8, size: usize) {
+    ptr::write_bytes(ptr, POISON, size);
+}
+
+/// Stops tracking the objects retired through the collector identified by `owner` that haven't
+/// been freed, and returns them.
+pub fn leaked(owner: usize) -> Vec<usize> {
+    let mut table = table();
+    let leaked: Vec<usize> = table
+        .retired
+        .iter()
+        .filter(|&(_, &o)| o == owner)
+        .map(|(&ptr, _)| ptr)
+        .collect();
+    for ptr in &leaked {
+        table.retired.remove(ptr);
+    }
+    leaked
+}
+
+/// Reports the objects retired through the collector identified by `owner` that haven't been
+/// freed.
+pub fn report_leaks(owner: usize) {
+    let leaked = leaked(owner);
+    if !leaked.is_empty() {
+        eprintln!(
+            "crossbeam-epoch: {} retired pointer(s) were never freed: {:x?}",
+            leaked.len(),
+            leaked
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering::Relaxed;
+
+    use {unprotected, Atomic, Collector, Owned, Shared};
+    use super::leaked;
+
+    #[test]
+    fn defer_destroy() {
+        let collector = Collector::new();
+        let handle = collector.register();
+
+        for _ in 0..1000 {
+            let guard = &handle.pin();
+            let p = Owned::new([0u64; 4]).into_shared(guard);
+            unsafe { guard.defer_destroy(p) };
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "retired twice")]
+    fn double_retire() {
+        let collector = Collector::new();
+        let handle = collector.register();
+        let guard = &handle.pin();
+
+        let p = Owned::new(1).into_shared(guard);
+        unsafe {
+            guard.defer_destroy(p);
+            guard.defer_destroy(p);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "will be freed by the collector")]
+    fn into_owned_retired() {
+        let collector = Collector::new();
+        let handle = collector.register();
+        let guard = &handle.pin();
+
+        let a = Atomic::new(1);
+        let p = a.swap(Shared::null(), Relaxed, guard);
+        unsafe {
+            guard.defer_destroy(p);
+            drop(p.into_owned());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "will be freed by the collector")]
+    fn into_owned_retired_slice() {
+        let collector = Collector::new();
+        let handle = collector.register();
+        let guard = &handle.pin();
+
+        let a = Atomic::<[u64]>::init(vec![1, 2, 3]);
+        let p = a.swap(Shared::null(), Relaxed, guard);
+        unsafe {
+            guard.defer_destroy(p);
+            drop(p.into_owned());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "already freed")]
+    fn double_free() {
+        let p = Owned::new(1).into_shared(unsafe { unprotected() });
+        unsafe {
+            drop(p.into_owned());
+            drop(p.into_owned());
+        }
+    }
+
+    #[test]
+    fn leaks() {
+        let o = Owned::new(1);
+        let p = &*o as *const i32 as usize;
+        super::retire(p, 1);
+        assert_eq!(leaked(1), [p]);
+        assert!(leaked(1).is_empty());
+    }
+}
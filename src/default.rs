@@ -7,16 +7,29 @@
 use collector::{Collector, LocalHandle};
 use guard::Guard;
 
+#[cfg(not(crossbeam_loom))]
 lazy_static! {
     /// The global data for the default garbage collector.
     static ref COLLECTOR: Collector = Collector::new();
 }
 
+#[cfg(not(crossbeam_loom))]
 thread_local! {
     /// The per-thread participant for the default garbage collector.
     static HANDLE: LocalHandle = COLLECTOR.register();
 }
 
+// Under loom, the collector and the participants are reset between executions of a model.
+#[cfg(crossbeam_loom)]
+::loom::lazy_static! {
+    static ref COLLECTOR: Collector = Collector::new();
+}
+
+#[cfg(crossbeam_loom)]
+::loom::thread_local! {
+    static HANDLE: LocalHandle = COLLECTOR.register();
+}
+
 /// Pins the current thread.
 #[inline]
 pub fn pin() -> Guard {
@@ -29,6 +42,27 @@ pub fn is_pinned() -> bool {
     with_handle(|handle| handle.is_pinned())
 }
 
+/// Blocks until every thread that was pinned at the time of the call gets unpinned.
+///
+/// This is the default collector's counterpart of [`Collector::synchronize`].
+///
+/// # Panics
+///
+/// Panics if the current thread is pinned.
+///
+/// # Examples
+///
+/// ```
+/// use crossbeam_epoch as epoch;
+///
+/// epoch::synchronize();
+/// ```
+///
+/// [`Collector::synchronize`]: struct.Collector.html#method.synchronize
+pub fn synchronize() {
+    with_handle(|handle| handle.synchronize())
+}
+
 /// Returns the default global collector.
 pub fn default_collector() -> &'static Collector {
     &COLLECTOR
@@ -39,7 +73,12 @@ fn with_handle<F, R>(mut f: F) -> R
 where
     F: FnMut(&LocalHandle) -> R,
 {
-    HANDLE.try_with(|h| f(h)).unwrap_or_else(|_| f(&COLLECTOR.register()))
+    HANDLE.try_with(|h| f(h)).unwrap_or_else(|_| {
+        // The thread is exiting, so the temporary participant must not look it up.
+        let handle = COLLECTOR.register();
+        unsafe { (*handle.local).forget_thread() };
+        f(&handle)
+    })
 }
 
 #[cfg(test)]
@@ -70,4 +109,11 @@ mod tests {
             });
         });
     }
+
+    #[test]
+    #[should_panic]
+    fn synchronize_while_pinned() {
+        let _guard = super::pin();
+        super::default_collector().synchronize();
+    }
 }
@@ -7,7 +7,9 @@
 //! If an object became garbage in some epoch, then we can be sure that after two advancements no
 //! participant will hold a reference to it. That is the crux of safe memory reclamation.
 
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::sync::atomic::Ordering;
+
+use primitive::sync::atomic::AtomicUsize;
 
 /// An epoch that can be marked as pinned or unpinned.
 ///
@@ -55,6 +57,14 @@ impl Epoch {
         Epoch { data: self.data & !1 }
     }
 
+    /// Returns the position of the epoch in a cycle of `len` consecutive epochs.
+    ///
+    /// Successive epochs have successive positions, except when the epoch counter wraps around.
+    #[inline]
+    pub fn position(self, len: usize) -> usize {
+        (self.data >> 1) % len
+    }
+
     /// Returns the successor epoch.
     ///
     /// The returned epoch will be marked as pinned only if the previous one was as well.
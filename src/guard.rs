@@ -2,10 +2,11 @@ use core::fmt;
 use core::ptr;
 use core::mem;
 
-use atomic::Shared;
+use atomic::{Pointable, Shared};
 use collector::Collector;
 use deferred::Deferred;
 use internal::Local;
+use limit::TryDeferError;
 
 /// A guard that keeps the current thread pinned.
 ///
@@ -96,7 +97,12 @@ impl Guard {
     /// If this method is called from an [`unprotected`] guard, the function will simply be
     /// executed immediately.
     ///
+    /// If the collector has a [`GarbageLimit`] that is currently exceeded, its [`GarbagePolicy`]
+    /// is applied before `f` is stored, which may block the current thread.
+    ///
     /// [`unprotected`]: fn.unprotected.html
+    /// [`GarbageLimit`]: enum.GarbageLimit.html
+    /// [`GarbagePolicy`]: enum.GarbagePolicy.html
     pub fn defer<F, R>(&self, f: F)
     where
         F: FnOnce() -> R,
@@ -107,6 +113,54 @@ impl Guard {
         }
     }
 
+    /// Stores a function so that it can be executed at some point after all currently pinned
+    /// threads get unpinned, unless the collector's garbage limit is exceeded.
+    ///
+    /// This method behaves like [`defer`], except that it never applies the collector's
+    /// [`GarbagePolicy`]. If the [`GarbageLimit`] is exceeded, `f` is returned back inside the
+    /// error instead.
+    ///
+    /// If this method is called from an [`unprotected`] guard, it always succeeds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{CollectorBuilder, GarbageLimit};
+    ///
+    /// let collector = CollectorBuilder::new()
+    ///     .garbage_limit(GarbageLimit::Deferreds(1024))
+    ///     .build();
+    /// let handle = collector.register();
+    ///
+    /// let guard = &handle.pin();
+    /// if let Err(err) = guard.try_defer(move || println!("deferred")) {
+    ///     // Too much garbage is waiting to be collected, so run the function right away.
+    ///     (err.into_inner())();
+    /// }
+    /// ```
+    ///
+    /// [`defer`]: struct.Guard.html#method.defer
+    /// [`unprotected`]: fn.unprotected.html
+    /// [`GarbageLimit`]: enum.GarbageLimit.html
+    /// [`GarbagePolicy`]: enum.GarbagePolicy.html
+    pub fn try_defer<F, R>(&self, f: F) -> Result<(), TryDeferError<F>>
+    where
+        F: FnOnce() -> R,
+        F: Send + 'static,
+    {
+        if let Some(local) = unsafe { self.local.as_ref() } {
+            if local.global().is_over_limit() {
+                return Err(TryDeferError::new(f));
+            }
+            unsafe {
+                local.defer(Deferred::new(move || drop(f())), mem::size_of::<F>(), self);
+            }
+        } else {
+            drop(f());
+        }
+        Ok(())
+    }
+
     /// Stores a function so that it can be executed at some point after all currently pinned
     /// threads get unpinned.
     ///
@@ -123,6 +177,9 @@ impl Guard {
     /// If this method is called from an [`unprotected`] guard, the function will simply be
     /// executed immediately.
     ///
+    /// If the collector has a [`GarbageLimit`] that is currently exceeded, its [`GarbagePolicy`]
+    /// is applied before `f` is stored, which may block the current thread.
+    ///
     /// # Safety
     ///
     /// The given function must not hold reference onto the stack. It is highly recommended that
@@ -197,12 +254,25 @@ impl Guard {
     /// ```
     ///
     /// [`unprotected`]: fn.unprotected.html
+    /// [`GarbageLimit`]: enum.GarbageLimit.html
+    /// [`GarbagePolicy`]: enum.GarbagePolicy.html
     pub unsafe fn defer_unchecked<F, R>(&self, f: F)
+    where
+        F: FnOnce() -> R,
+    {
+        self.defer_sized(f, mem::size_of::<F>());
+    }
+
+    /// Stores a function like `defer_unchecked`, accounting `size` bytes to it.
+    unsafe fn defer_sized<F, R>(&self, f: F, size: usize)
     where
         F: FnOnce() -> R,
     {
         if let Some(local) = self.local.as_ref() {
-            local.defer(Deferred::new(move || drop(f())), self);
+            local.enforce_garbage_limit(self);
+            local.defer(Deferred::new(move || drop(f())), size, self);
+        } else {
+            drop(f());
         }
     }
 
@@ -222,6 +292,10 @@ impl Guard {
     /// If this method is called from an [`unprotected`] guard, the destructor will simply be
     /// executed immediately.
     ///
+    /// If the collector has a [`GarbageLimit`] that is currently exceeded, its [`GarbagePolicy`]
+    /// is applied before the destructor is stored, which may block the current thread. The size
+    /// of `T` is accounted towards [`GarbageLimit::Bytes`].
+    ///
     /// # Safety
     ///
     /// The object must not be reachable by other threads anymore, otherwise it might be still in
@@ -276,8 +350,25 @@ impl Guard {
     /// ```
     ///
     /// [`unprotected`]: fn.unprotected.html
-    pub unsafe fn defer_destroy<T>(&self, ptr: Shared<T>) {
-        self.defer_unchecked(move || ptr.into_owned());
+    /// [`GarbageLimit`]: enum.GarbageLimit.html
+    /// [`GarbagePolicy`]: enum.GarbagePolicy.html
+    /// [`GarbageLimit::Bytes`]: enum.GarbageLimit.html#variant.Bytes
+    pub unsafe fn defer_destroy<T: ?Sized + Pointable>(&self, ptr: Shared<T>) {
+        // Objects are tracked by the address of their allocation, which for slices is the address
+        // of the header rather than of the elements.
+        #[cfg(feature = "debug-reclaim")]
+        ::debug::retire(
+            ptr.address(),
+            self.local.as_ref().map_or(0, |local| local.global() as *const _ as usize),
+        );
+        self.defer_sized(
+            move || {
+                #[cfg(feature = "debug-reclaim")]
+                ::debug::reclaim(ptr.address());
+                ptr.into_owned()
+            },
+            mem::size_of_val(ptr.deref()),
+        );
     }
 
     /// Clears up the thread-local cache of deferred functions by executing them or moving into the
@@ -0,0 +1,691 @@
+//! Hazard-pointer-based memory reclamation.
+//!
+//! Epoch-based reclamation makes reads very cheap, but a single participant that stays pinned
+//! prevents all garbage from being destroyed. Hazard pointers trade some read-side cost for a
+//! bound on the amount of garbage: a thread announces every object it is about to read by
+//! publishing its address in a *hazard pointer*, and an unlinked object is destroyed as soon as no
+//! hazard pointer points to it.
+//!
+//! Hazard pointers work with the same [`Atomic`], [`Owned`] and [`Shared`] pointers as the
+//! epoch-based collector, and garbage is stored as the same deferred functions in the same kind
+//! of bags, so a data structure only needs to change how it loads pointers and how it defers
+//! destruction.
+//!
+//! # Domains
+//!
+//! Hazard pointers and retired objects belong to a [`Domain`]. Each thread registers a [`Handle`]
+//! with the domain, which keeps a list of objects retired by that thread, and acquires
+//! [`HazardPointer`]s from it.
+//!
+//! Once a handle has retired enough objects, it scans all hazard pointers of the domain and
+//! destroys the retired objects none of them points to. The number of retired objects that are
+//! not yet destroyed is therefore bounded by the number of hazard pointers and handles.
+//!
+//! # Examples
+//!
+//! ```
+//! use crossbeam_epoch::{hazard::Domain, Atomic, Owned};
+//! use std::sync::atomic::Ordering::{Acquire, AcqRel};
+//!
+//! let domain = Domain::new();
+//! let handle = domain.register();
+//! let a = Atomic::new(1);
+//!
+//! let mut hp = handle.hazard_pointer();
+//! let p = hp.protect(&a, Acquire);
+//! assert_eq!(unsafe { *p.deref() }, 1);
+//!
+//! // Replace the value and retire the old one. It is not destroyed while `hp` protects it.
+//! let old = a.swap(Owned::new(2), AcqRel, unsafe { crossbeam_epoch::unprotected() });
+//! unsafe { handle.defer_destroy(old) };
+//! assert_eq!(unsafe { *p.deref() }, 1);
+//!
+//! drop(hp);
+//! handle.reclaim();
+//! # unsafe { drop(a.load(Acquire, crossbeam_epoch::unprotected()).into_owned()) };
+//! ```
+//!
+//! Ideas from Michael.  Hazard Pointers: Safe Memory Reclamation for Lock-Free Objects.  IEEE
+//! TPDS 2004.  https://doi.org/10.1109/TPDS.2004.8
+//!
+//! [`Atomic`]: ../struct.Atomic.html
+//! [`Owned`]: ../struct.Owned.html
+//! [`Shared`]: ../struct.Shared.html
+//! [`Domain`]: struct.Domain.html
+//! [`Handle`]: struct.Handle.html
+//! [`HazardPointer`]: struct.HazardPointer.html
+
+use core::cell::UnsafeCell;
+use core::cmp;
+use core::fmt;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{self, AtomicBool, AtomicPtr, AtomicUsize};
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release, SeqCst};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crossbeam_utils::CachePadded;
+
+use atomic::{Atomic, Pointable, Shared};
+use deferred::Deferred;
+use epoch::Epoch;
+use guard::unprotected;
+use internal::{Bag, Bucket, Garbage};
+
+/// Minimum number of retired objects a handle accumulates before scanning the hazard pointers.
+#[cfg(not(feature = "sanitize"))]
+const MIN_RETIRED: usize = 64;
+#[cfg(feature = "sanitize")]
+const MIN_RETIRED: usize = 4;
+
+/// A slot holding a single hazard pointer.
+///
+/// Records are never deallocated while the domain is alive. A released record is reused by the
+/// next hazard pointer acquired from the domain.
+struct Record {
+    /// The protected address, or zero if the record protects nothing.
+    hazard: CachePadded<AtomicUsize>,
+
+    /// Whether the record is owned by a `HazardPointer`.
+    active: AtomicBool,
+
+    /// The next record in the domain's list of records.
+    next: *const Record,
+}
+
+/// An object that was retired, together with the function destroying it.
+#[derive(Debug)]
+struct Retired {
+    /// The address of the object.
+    ptr: usize,
+
+    /// The function to execute once no hazard pointer protects `ptr`.
+    deferred: Deferred,
+}
+
+impl Garbage for Retired {
+    fn dispose(self) {
+        self.deferred.call();
+    }
+}
+
+/// The data shared by all handles of a domain.
+struct Global {
+    /// The head of the append-only list of records.
+    records: AtomicPtr<Record>,
+
+    /// The number of records in the list.
+    record_count: AtomicUsize,
+
+    /// Bags of retired objects left behind by handles that were dropped while some of the objects
+    /// were protected.
+    ///
+    /// The epochs the bags are sealed with are meaningless, since the objects are destroyed once
+    /// they are not protected rather than once an epoch expires.
+    orphans: Bucket<Retired>,
+}
+
+impl Global {
+    /// Acquires an inactive record, allocating a new one if there is none.
+    fn acquire(&self) -> &Record {
+        let mut current = self.records.load(Acquire);
+        while let Some(record) = unsafe { current.as_ref() } {
+            if !record.active.load(Relaxed)
+                && record.active.compare_exchange(false, true, Acquire, Relaxed).is_ok()
+            {
+                return record;
+            }
+            current = record.next as *mut Record;
+        }
+
+        let record = Box::into_raw(Box::new(Record {
+            hazard: CachePadded::new(AtomicUsize::new(0)),
+            active: AtomicBool::new(true),
+            next: ptr::null(),
+        }));
+        let mut head = self.records.load(Relaxed);
+        loop {
+            unsafe { (*record).next = head };
+            match self.records.compare_exchange_weak(head, record, Release, Relaxed) {
+                Ok(_) => break,
+                Err(h) => head = h,
+            }
+        }
+        self.record_count.fetch_add(1, Relaxed);
+        unsafe { &*record }
+    }
+
+    /// Returns the sorted addresses currently protected by hazard pointers.
+    fn hazards(&self) -> Vec<usize> {
+        // Pairs with the fence in `HazardPointer::try_protect`: either the protecting thread sees
+        // that the object was unlinked, or we see its hazard pointer.
+        atomic::fence(SeqCst);
+
+        let mut hazards = Vec::new();
+        let mut current = self.records.load(Acquire);
+        while let Some(record) = unsafe { current.as_ref() } {
+            let hazard = record.hazard.load(Relaxed);
+            if hazard != 0 {
+                hazards.push(hazard);
+            }
+            current = record.next as *mut Record;
+        }
+        hazards.sort_unstable();
+        hazards
+    }
+
+    /// Hands the bag of retired objects over to the other handles of the domain.
+    fn push_orphan(&self, bag: Bag<Retired>) {
+        self.orphans.push(bag.seal(Epoch::starting()), unsafe { unprotected() });
+    }
+
+    /// Takes all orphaned retired objects, appending them to `retired`.
+    fn adopt_orphans(&self, retired: &mut Vec<Retired>) {
+        self.orphans.take_each(|sealed_bag| retired.extend(sealed_bag.into_bag().drain()));
+    }
+}
+
+impl Drop for Global {
+    fn drop(&mut self) {
+        // No handles are alive, so no object can be protected anymore.
+        self.orphans.clear();
+
+        let mut current = *self.records.get_mut();
+        while !current.is_null() {
+            let record = unsafe { Box::from_raw(current) };
+            current = record.next as *mut Record;
+        }
+
+        #[cfg(feature = "debug-reclaim")]
+        ::debug::report_leaks(self as *const Global as usize);
+    }
+}
+
+/// A hazard pointer domain.
+///
+/// Objects retired in a domain are destroyed once no hazard pointer of the same domain protects
+/// them. Cloning a domain returns another reference to it.
+pub struct Domain {
+    global: Arc<Global>,
+}
+
+unsafe impl Send for Domain {}
+unsafe impl Sync for Domain {}
+
+impl Domain {
+    /// Creates a new domain.
+    pub fn new() -> Self {
+        Domain {
+            global: Arc::new(Global {
+                records: AtomicPtr::new(ptr::null_mut()),
+                record_count: AtomicUsize::new(0),
+                orphans: Bucket::new(),
+            }),
+        }
+    }
+
+    /// Registers a new handle for the domain.
+    pub fn register(&self) -> Handle {
+        Handle {
+            domain: self.clone(),
+            retired: UnsafeCell::new(Bag::with_capacity(MIN_RETIRED)),
+        }
+    }
+}
+
+impl Clone for Domain {
+    /// Creates another reference to the same domain.
+    fn clone(&self) -> Self {
+        Domain {
+            global: self.global.clone(),
+        }
+    }
+}
+
+impl Default for Domain {
+    fn default() -> Self {
+        Domain::new()
+    }
+}
+
+impl PartialEq for Domain {
+    /// Checks if both handles point to the same domain.
+    fn eq(&self, rhs: &Domain) -> bool {
+        Arc::ptr_eq(&self.global, &rhs.global)
+    }
+}
+impl Eq for Domain {}
+
+impl fmt::Debug for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Domain").finish()
+    }
+}
+
+/// A handle to a hazard pointer domain.
+///
+/// A handle acquires hazard pointers and keeps the objects retired through it until they can be
+/// destroyed. When the handle is dropped, the objects that are still protected are handed over to
+/// the other handles of the domain.
+pub struct Handle {
+    domain: Domain,
+
+    /// Objects retired through this handle that may still be protected.
+    ///
+    /// The hazard pointers are scanned once the bag is full, and the capacity of the bag is
+    /// adjusted to the number of hazard pointers on every scan.
+    retired: UnsafeCell<Bag<Retired>>,
+}
+
+impl Handle {
+    /// Returns the domain this handle belongs to.
+    pub fn domain(&self) -> &Domain {
+        &self.domain
+    }
+
+    /// Acquires a hazard pointer that protects nothing.
+    pub fn hazard_pointer<'h>(&'h self) -> HazardPointer<'h> {
+        HazardPointer {
+            record: self.domain.global.acquire(),
+        }
+    }
+
+    /// Stores a destructor for an object so that it can be deallocated and dropped once no hazard
+    /// pointer protects it.
+    ///
+    /// This is the counterpart of [`Guard::defer_destroy`].
+    ///
+    /// # Safety
+    ///
+    /// The object must not be reachable by other threads anymore, i.e. no hazard pointer may start
+    /// protecting it after this call. Hazard pointers that already protect it keep it alive.
+    ///
+    /// [`Guard::defer_destroy`]: ../struct.Guard.html#method.defer_destroy
+    pub unsafe fn defer_destroy<T: ?Sized + Pointable>(&self, ptr: Shared<T>) {
+        #[cfg(feature = "debug-reclaim")]
+        ::debug::retire(
+            ptr.address(),
+            &*self.domain.global as *const Global as usize,
+        );
+        self.defer_unchecked(ptr, move || {
+            #[cfg(feature = "debug-reclaim")]
+            ::debug::reclaim(ptr.address());
+            ptr.into_owned()
+        });
+    }
+
+    /// Stores a function so that it can be executed once no hazard pointer protects the object
+    /// `ptr` points to.
+    ///
+    /// This is the counterpart of [`Guard::defer_unchecked`], except that the function waits for
+    /// hazard pointers to a single object instead of all pinned participants.
+    ///
+    /// # Safety
+    ///
+    /// The object must not be reachable by other threads anymore, and it must be safe for another
+    /// thread to execute `f`.
+    ///
+    /// [`Guard::defer_unchecked`]: ../struct.Guard.html#method.defer_unchecked
+    pub unsafe fn defer_unchecked<T, F, R>(&self, ptr: Shared<T>, f: F)
+    where
+        T: ?Sized + Pointable,
+        F: FnOnce() -> R,
+    {
+        let mut retired = Retired {
+            ptr: ptr.address(),
+            deferred: Deferred::new(move || drop(f())),
+        };
+
+        // A scan leaves at least half of the bag empty, so this loops at most once.
+        while let Err(r) = (*self.retired.get()).try_push(retired) {
+            self.reclaim();
+            retired = r;
+        }
+    }
+
+    /// Destroys the retired objects that are not protected by any hazard pointer.
+    ///
+    /// This happens automatically once enough objects have been retired, but can be called to
+    /// destroy them as soon as possible.
+    pub fn reclaim(&self) {
+        let global = &self.domain.global;
+        let ready = unsafe {
+            let bag = &mut *self.retired.get();
+            let mut retired: Vec<_> = bag.drain().collect();
+            global.adopt_orphans(&mut retired);
+
+            let hazards = global.hazards();
+            let (protected, ready): (Vec<_>, Vec<_>) = retired
+                .into_iter()
+                .partition(|r| hazards.binary_search(&r.ptr).is_ok());
+
+            // Every hazard pointer protects at most one object, so this leaves room for at least
+            // as many objects as there are hazard pointers.
+            let record_count = global.record_count.load(Relaxed);
+            let capacity = cmp::max(MIN_RETIRED, 2 * cmp::max(record_count, protected.len()));
+            if bag.capacity() != capacity {
+                *bag = Bag::with_capacity(capacity);
+            }
+            for r in protected {
+                let res = bag.try_push(r);
+                debug_assert!(res.is_ok());
+            }
+            ready
+        };
+
+        // The functions may retire more objects, so they are called only after the bag of retired
+        // objects has been updated.
+        for r in ready {
+            r.dispose();
+        }
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.reclaim();
+
+        let bag = unsafe { &mut *self.retired.get() };
+        if !bag.is_empty() {
+            let bag = mem::replace(bag, Bag::with_capacity(1));
+            self.domain.global.push_orphan(bag);
+        }
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Handle").finish()
+    }
+}
+
+/// A hazard pointer, protecting a single object from being destroyed.
+///
+/// Loading a pointer through a hazard pointer returns a [`Shared`] that can be dereferenced until
+/// the hazard pointer protects another object or is dropped.
+///
+/// [`Shared`]: ../struct.Shared.html
+pub struct HazardPointer<'h> {
+    record: &'h Record,
+}
+
+impl<'h> HazardPointer<'h> {
+    /// Loads a pointer from `atomic` and protects the object it points to.
+    ///
+    /// The returned pointer may be dereferenced as long as the hazard pointer is not used to
+    /// protect another object, provided that objects unlinked from `atomic` are retired in the
+    /// same domain.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{hazard::Domain, Atomic};
+    /// use std::sync::atomic::Ordering::Acquire;
+    ///
+    /// let handle = Domain::new().register();
+    /// let a = Atomic::new(1234);
+    ///
+    /// let mut hp = handle.hazard_pointer();
+    /// let p = hp.protect(&a, Acquire);
+    /// assert_eq!(unsafe { p.as_ref() }, Some(&1234));
+    /// # unsafe { drop(a.load(Acquire, crossbeam_epoch::unprotected()).into_owned()) };
+    /// ```
+    pub fn protect<'a, T>(&'a mut self, atomic: &Atomic<T>, ord: atomic::Ordering) -> Shared<'a, T>
+    where
+        T: ?Sized + Pointable,
+    {
+        let mut ptr = atomic.load(Relaxed, unsafe { unprotected() });
+        loop {
+            match self.publish(ptr, atomic, ord) {
+                Ok(ptr) => return ptr,
+                Err(current) => ptr = current,
+            }
+        }
+    }
+
+    /// Protects the object `ptr` points to if `atomic` still holds `ptr`.
+    ///
+    /// Returns `ptr` if the object is now protected, and otherwise the current value of `atomic`,
+    /// which is not protected. This is useful for traversals where the object a pointer was loaded
+    /// from is protected by another hazard pointer.
+    pub fn try_protect<'a, T>(
+        &'a mut self,
+        ptr: Shared<T>,
+        atomic: &Atomic<T>,
+        ord: atomic::Ordering,
+    ) -> Result<Shared<'a, T>, Shared<'a, T>>
+    where
+        T: ?Sized + Pointable,
+    {
+        self.publish(ptr, atomic, ord)
+    }
+
+    /// Publishes `ptr` in the hazard pointer and checks whether `atomic` still holds it.
+    fn publish<'a, T>(
+        &self,
+        ptr: Shared<T>,
+        atomic: &Atomic<T>,
+        ord: atomic::Ordering,
+    ) -> Result<Shared<'a, T>, Shared<'a, T>>
+    where
+        T: ?Sized + Pointable + 'a,
+    {
+        self.record.hazard.store(ptr.address(), Relaxed);
+
+        // Pairs with the fence in `Global::hazards`: either a thread scanning the hazard pointers
+        // sees this one, or the following load sees that the object was unlinked.
+        atomic::fence(SeqCst);
+
+        let current = atomic.load(ord, unsafe { unprotected() });
+        if current == ptr {
+            Ok(current)
+        } else {
+            Err(current)
+        }
+    }
+
+    /// Stops protecting the object.
+    pub fn reset(&mut self) {
+        self.record.hazard.store(0, Release);
+    }
+}
+
+impl<'h> Drop for HazardPointer<'h> {
+    fn drop(&mut self) {
+        self.record.hazard.store(0, Release);
+        self.record.active.store(false, Release);
+    }
+}
+
+impl<'h> fmt::Debug for HazardPointer<'h> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HazardPointer")
+            .field("hazard", &(self.record.hazard.load(Relaxed) as *const ()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed};
+
+    use crossbeam_utils::thread;
+
+    use {unprotected, Atomic, Owned};
+    use super::{Domain, MIN_RETIRED};
+
+    #[test]
+    fn protect_defer() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(usize);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Relaxed);
+            }
+        }
+
+        let domain = Domain::new();
+        let handle = domain.register();
+        let a = Atomic::new(Elem(0));
+
+        let mut hp = handle.hazard_pointer();
+        let protected = hp.protect(&a, Acquire);
+
+        for i in 1..MIN_RETIRED * 4 {
+            let old = a.swap(Owned::new(Elem(i)), AcqRel, unsafe { unprotected() });
+            unsafe { handle.defer_destroy(old) };
+        }
+        handle.reclaim();
+
+        // Everything except the protected object has been destroyed.
+        assert_eq!(DROPS.load(Relaxed), MIN_RETIRED * 4 - 2);
+        assert_eq!(unsafe { protected.deref().0 }, 0);
+
+        drop(hp);
+        handle.reclaim();
+        assert_eq!(DROPS.load(Relaxed), MIN_RETIRED * 4 - 1);
+
+        unsafe { drop(a.load(Relaxed, unprotected()).into_owned()) };
+        assert_eq!(DROPS.load(Relaxed), MIN_RETIRED * 4);
+    }
+
+    #[test]
+    fn try_protect() {
+        let handle = Domain::new().register();
+        let a = Atomic::new(1);
+        let mut hp = handle.hazard_pointer();
+
+        let p = a.load(Relaxed, unsafe { unprotected() });
+        assert_eq!(hp.try_protect(p, &a, Acquire), Ok(p));
+
+        let q = a.swap(Owned::new(2), AcqRel, unsafe { unprotected() });
+        assert_eq!(hp.try_protect(p, &a, Acquire).unwrap_err(), a.load(Relaxed, unsafe {
+            unprotected()
+        }));
+
+        unsafe {
+            handle.defer_destroy(q);
+            drop(a.load(Relaxed, unprotected()).into_owned());
+        }
+    }
+
+    #[test]
+    fn records_reused() {
+        let domain = Domain::new();
+        let handle = domain.register();
+        for _ in 0..10 {
+            let _a = handle.hazard_pointer();
+            let _b = handle.hazard_pointer();
+        }
+        assert_eq!(domain.global.record_count.load(Relaxed), 2);
+    }
+
+    #[test]
+    fn orphans() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem;
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Relaxed);
+            }
+        }
+
+        let domain = Domain::new();
+        let reader = domain.register();
+        let a = Atomic::new(Elem);
+
+        let mut hp = reader.hazard_pointer();
+        hp.protect(&a, Acquire);
+
+        let writer = domain.register();
+        let old = a.swap(Owned::new(Elem), AcqRel, unsafe { unprotected() });
+        unsafe { writer.defer_destroy(old) };
+        drop(writer);
+        assert_eq!(DROPS.load(Relaxed), 0);
+
+        // The object retired by `writer` is adopted by `reader`.
+        drop(hp);
+        reader.reclaim();
+        assert_eq!(DROPS.load(Relaxed), 1);
+
+        let old = a.swap(Owned::new(Elem), AcqRel, unsafe { unprotected() });
+        unsafe { reader.defer_destroy(old) };
+        drop(reader);
+        drop(domain);
+        assert_eq!(DROPS.load(Relaxed), 2);
+
+        unsafe { drop(a.load(Relaxed, unprotected()).into_owned()) };
+    }
+
+    #[test]
+    fn many_protected() {
+        let domain = Domain::new();
+        let handle = domain.register();
+        let a = Atomic::new(0);
+
+        // Protect more retired objects than fit into the initial bag.
+        let mut hps = Vec::new();
+        for i in 1..MIN_RETIRED * 3 {
+            let mut hp = handle.hazard_pointer();
+            hp.protect(&a, Acquire);
+            hps.push(hp);
+            let old = a.swap(Owned::new(i), AcqRel, unsafe { unprotected() });
+            unsafe { handle.defer_destroy(old) };
+        }
+        handle.reclaim();
+        assert_eq!(unsafe { (*handle.retired.get()).len() }, MIN_RETIRED * 3 - 1);
+
+        drop(hps);
+        handle.reclaim();
+        assert!(unsafe { (*handle.retired.get()).is_empty() });
+
+        unsafe { drop(a.load(Relaxed, unprotected()).into_owned()) };
+    }
+
+    #[test]
+    fn concurrent_swap() {
+        const COUNT: usize = 10_000;
+        const THREADS: usize = 4;
+
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(usize);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Relaxed);
+            }
+        }
+
+        let domain = Domain::new();
+        let a = Atomic::new(Elem(0));
+
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| {
+                    let handle = domain.register();
+                    for i in 0..COUNT {
+                        {
+                            let mut hp = handle.hazard_pointer();
+                            let p = hp.protect(&a, Acquire);
+                            assert!(unsafe { p.deref().0 } < COUNT);
+                        }
+                        let old = a.swap(Owned::new(Elem(i)), AcqRel, unsafe { unprotected() });
+                        unsafe { handle.defer_destroy(old) };
+                    }
+                });
+            }
+        });
+
+        drop(domain);
+        assert_eq!(DROPS.load(Relaxed), COUNT * THREADS);
+        unsafe { drop(a.load(Relaxed, unprotected()).into_owned()) };
+    }
+}
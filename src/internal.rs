@@ -4,8 +4,10 @@
 //!
 //! In order to track all participants in one place, we need some form of participant
 //! registration. When a participant is created, it is registered to a global lock-free
-//! singly-linked list of registries; and when a participant is leaving, it is unregistered from the
-//! list.
+//! singly-linked list of registries; and when a participant is leaving, its registry is marked as
+//! free. Free registries are reused by later participants, so the list only grows up to the
+//! maximum number of participants alive at the same time, and is destroyed along with the global
+//! data.
 //!
 //! # Pinning
 //!
@@ -21,59 +23,182 @@
 //! Objects that get unlinked from concurrent data structures must be stashed away until the global
 //! epoch sufficiently advances so that they become safe for destruction. Pointers to such objects
 //! are pushed into a thread-local bag, and when it becomes full, the bag is marked with the current
-//! global epoch and pushed into the global queue of bags. We store objects in thread-local storages
-//! for amortizing the synchronization cost of pushing the garbages to a global queue.
+//! global epoch and pushed into the global garbage. We store objects in thread-local storages for
+//! amortizing the synchronization cost of pushing the garbages to the global garbage.
 //!
-//! # Global queue
+//! # Global garbage
 //!
-//! Whenever a bag is pushed into a queue, the objects in some bags in the queue are collected and
-//! destroyed along the way. This design reduces contention on data structures. The global queue
-//! cannot be explicitly accessed: the only way to interact with it is by calling functions
-//! `defer()` that adds an object tothe thread-local bag, or `collect()` that manually triggers
-//! garbage collection.
+//! Sealed bags are kept in three buckets, and each bag goes into the bucket of the epoch it was
+//! marked with. Bags from the current and the previous epoch cannot be destroyed yet, while bags
+//! from two epochs ago are expired, and their bucket is the one that will receive the bags of the
+//! next epoch. Collection therefore detaches that bucket in a single operation and destroys its
+//! bags along the way. Threads pushing bags only contend on the bucket of the current epoch, and
+//! threads collecting garbage only on the bucket of the expired one.
 //!
-//! Ideally each instance of concurrent data structure may have its own queue that gets fully
+//! A participant may mark its bag with a stale epoch, and a collecting thread may work with a
+//! stale global epoch, so any bucket may turn out to hold expired bags, and a bucket may receive a
+//! fresh bag just before it is detached. Each bucket therefore remembers the epoch of its newest
+//! bag, and is only detached as a whole once that epoch is expired. If a fresh bag raced in, the
+//! whole chain is put back and destroyed once the bucket is detached again.
+//!
+//! A single collection destroys a bounded number of bags. If a detached chain is longer, the rest
+//! of it is put back as well.
+//!
+//! The global garbage cannot be explicitly accessed: the only way to interact with it is by calling
+//! functions `defer()` that adds an object tothe thread-local bag, or `collect()` that manually
+//! triggers garbage collection.
+//!
+//! Ideally each instance of concurrent data structure may have its own garbage that gets fully
 //! destroyed as soon as the data structure gets dropped.
 
-use core::cell::{Cell, UnsafeCell};
-use core::mem::{self, ManuallyDrop};
+use core::cell::Cell;
+use core::hint;
+use core::mem;
 use core::num::Wrapping;
+#[cfg(feature = "use_std")]
 use core::ptr;
-use core::sync::atomic;
+use core::slice;
 use core::sync::atomic::Ordering;
 use alloc::boxed::Box;
+use alloc::vec::{self, Vec};
+#[cfg(all(feature = "use_std", not(crossbeam_loom)))]
+use std::thread::{self, ThreadId};
+#[cfg(feature = "use_std")]
+use std::time::Duration;
 
 use crossbeam_utils::CachePadded;
-use arrayvec::ArrayVec;
 
-use atomic::Owned;
-use collector::{LocalHandle, Collector};
+use atomic::{Atomic, Owned, Shared};
+use collector::{LocalHandle, Collector, TryRegisterError};
 use epoch::{AtomicEpoch, Epoch};
 use guard::{unprotected, Guard};
 use deferred::Deferred;
-use sync::list::{List, Entry, IterError, IsElement};
-use sync::queue::Queue;
-
-/// Maximum number of objects a bag can contain.
+use limit::{GarbageLimit, GarbagePolicy};
+use membarrier;
+use primitive::cell::UnsafeCell;
+use primitive::sync::atomic::{self, AtomicBool};
+#[cfg(feature = "use_std")]
+use stall::{StallCallback, StallInfo, StalledParticipant};
+use stats::Counters;
+use sync::list::{List, Entry, Iter, IterError};
+
+/// Default maximum number of objects a bag can contain.
 #[cfg(not(feature = "sanitize"))]
 const MAX_OBJECTS: usize = 64;
 #[cfg(feature = "sanitize")]
 const MAX_OBJECTS: usize = 4;
 
+/// Number of buckets of sealed bags.
+///
+/// Bags from the current and the previous epoch are not expired, so one more bucket suffices for
+/// the expired ones.
+const BUCKETS: usize = 3;
+
+/// Tunable parameters of a garbage collector.
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    /// Maximum number of deferred functions a bag can contain.
+    pub bag_capacity: usize,
+
+    /// Maximum number of sealed bags destroyed in a single call to `Global::collect`.
+    pub collect_steps: usize,
+
+    /// Number of pinnings after which a participant will execute some deferred functions from the
+    /// global queue.
+    pub pinnings_between_collect: usize,
+
+    /// Whether pinning and flushing may execute deferred functions from the global queue.
+    ///
+    /// If disabled, garbage is collected only by explicit calls to `Global::collect`, e.g. from a
+    /// background reclaimer thread.
+    pub collect_on_pin: bool,
+
+    /// Whether pinning relies on `membarrier` instead of executing a `SeqCst` fence.
+    ///
+    /// This may only be enabled if `membarrier::is_supported` returned `true`.
+    pub membarrier: bool,
+
+    /// The limit on the amount of garbage in the global queue, if any.
+    pub garbage_limit: Option<GarbageLimit>,
+
+    /// What happens when a participant defers a function while the garbage limit is exceeded.
+    pub garbage_policy: GarbagePolicy,
+
+    /// The function called once a participant has been blocking the global epoch for longer than
+    /// the given threshold, if any.
+    #[cfg(feature = "use_std")]
+    pub stall_callback: Option<(Duration, StallCallback)>,
+
+    /// The number of preallocated participants, if participants are kept in a fixed-capacity
+    /// array instead of a linked list.
+    pub participant_capacity: Option<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bag_capacity: MAX_OBJECTS,
+            collect_steps: if cfg!(feature = "sanitize") {
+                usize::max_value()
+            } else {
+                Global::COLLECT_STEPS
+            },
+            pinnings_between_collect: Local::PINNINGS_BETWEEN_COLLECT,
+            collect_on_pin: true,
+            membarrier: false,
+            garbage_limit: None,
+            garbage_policy: GarbagePolicy::Block,
+            #[cfg(feature = "use_std")]
+            stall_callback: None,
+            participant_capacity: None,
+        }
+    }
+}
+
+/// An object stored in a `Bag`, which is disposed of when the bag is destroyed.
+pub trait Garbage {
+    /// Disposes of the object.
+    fn dispose(self);
+}
+
+impl Garbage for Deferred {
+    fn dispose(self) {
+        self.call();
+    }
+}
+
 /// A bag of deferred functions.
-#[derive(Default, Debug)]
-pub struct Bag {
+///
+/// Hazard pointer domains store other kinds of garbage in bags, hence the type parameter.
+#[derive(Debug)]
+pub struct Bag<G: Garbage = Deferred> {
     /// Stashed objects.
-    deferreds: ArrayVec<[Deferred; MAX_OBJECTS]>,
+    deferreds: Vec<G>,
+
+    /// Maximum number of objects the bag can contain.
+    capacity: usize,
+
+    /// Number of bytes accounted to the stashed objects.
+    bytes: usize,
 }
 
 /// `Bag::try_push()` requires that it is safe for another thread to execute the given functions.
-unsafe impl Send for Bag {}
+unsafe impl<G: Garbage> Send for Bag<G> {}
 
-impl Bag {
-    /// Returns a new, empty bag.
+impl<G: Garbage> Bag<G> {
+    /// Returns a new, empty bag with the default capacity.
     pub fn new() -> Self {
-        Self::default()
+        Self::with_capacity(MAX_OBJECTS)
+    }
+
+    /// Returns a new, empty bag that can contain up to `capacity` objects.
+    pub fn with_capacity(capacity: usize) -> Self {
+        debug_assert!(capacity > 0);
+        Bag {
+            deferreds: Vec::with_capacity(capacity),
+            capacity,
+            bytes: 0,
+        }
     }
 
     /// Returns `true` if the bag is empty.
@@ -81,6 +206,16 @@ impl Bag {
         self.deferreds.is_empty()
     }
 
+    /// Returns the number of deferred functions in the bag.
+    pub fn len(&self) -> usize {
+        self.deferreds.len()
+    }
+
+    /// Returns the maximum number of objects the bag can contain.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
     /// Attempts to insert a deferred function into the bag.
     ///
     /// Returns `Ok(())` if successful, and `Err(deferred)` for the given `deferred` if the bag is
@@ -89,36 +224,55 @@ impl Bag {
     /// # Safety
     ///
     /// It should be safe for another thread to execute the given function.
-    pub unsafe fn try_push(&mut self, deferred: Deferred) -> Result<(), Deferred> {
-        self.deferreds.try_push(deferred).map_err(|e| e.element())
+    pub unsafe fn try_push(&mut self, deferred: G) -> Result<(), G> {
+        if self.deferreds.len() < self.capacity {
+            self.deferreds.push(deferred);
+            Ok(())
+        } else {
+            Err(deferred)
+        }
+    }
+
+    /// Removes all objects from the bag without disposing of them.
+    pub fn drain<'a>(&'a mut self) -> vec::Drain<'a, G> {
+        self.bytes = 0;
+        self.deferreds.drain(..)
     }
 
     /// Seals the bag with the given epoch.
-    fn seal(self, epoch: Epoch) -> SealedBag {
+    pub fn seal(self, epoch: Epoch) -> SealedBag<G> {
         SealedBag { epoch, bag: self }
     }
 }
 
-impl Drop for Bag {
+impl<G: Garbage> Default for Bag<G> {
+    fn default() -> Self {
+        Bag::new()
+    }
+}
+
+impl<G: Garbage> Drop for Bag<G> {
     fn drop(&mut self) {
         // Call all deferred functions.
         for deferred in self.deferreds.drain(..) {
-            deferred.call();
+            deferred.dispose();
         }
     }
 }
 
 /// A pair of an epoch and a bag.
-#[derive(Default, Debug)]
-struct SealedBag {
+#[derive(Debug)]
+pub struct SealedBag<G: Garbage = Deferred> {
     epoch: Epoch,
-    bag: Bag,
+    bag: Bag<G>,
 }
 
-/// It is safe to share `SealedBag` because `is_expired` only inspects the epoch.
-unsafe impl Sync for SealedBag {}
+impl<G: Garbage> SealedBag<G> {
+    /// Returns the bag, discarding the epoch.
+    pub fn into_bag(self) -> Bag<G> {
+        self.bag
+    }
 
-impl SealedBag {
     /// Checks if it is safe to drop the bag w.r.t. the given global epoch.
     fn is_expired(&self, global_epoch: Epoch) -> bool {
         // A pinned participant can witness at most one epoch advancement. Therefore, any bag that
@@ -127,43 +281,229 @@ impl SealedBag {
     }
 }
 
+/// A bucket of sealed bags, implemented as a lock-free stack.
+///
+/// Bags are pushed one at a time, but removed only all at once by swapping out the head of the
+/// stack. The thread that removes them becomes the sole owner of the nodes, so they can be
+/// destroyed right away and the stack doesn't suffer from the ABA problem.
+///
+/// Bags pushed into the same bucket are sealed with non-decreasing epochs, because a participant
+/// can't seal a bag with an epoch two steps behind the global one. The bucket keeps the epoch of
+/// the newest bag, so all of its bags are expired once that epoch is.
+#[derive(Debug)]
+pub struct Bucket<G: Garbage = Deferred> {
+    head: CachePadded<Atomic<BagNode<G>>>,
+    epoch: AtomicEpoch,
+}
+
+/// A node in a `Bucket`.
+#[derive(Debug)]
+struct BagNode<G: Garbage = Deferred> {
+    sealed_bag: SealedBag<G>,
+    next: Atomic<BagNode<G>>,
+}
+
+// Bags are owned by a single thread at a time: the one that pushes them or takes them out.
+unsafe impl<G: Garbage> Send for Bucket<G> {}
+unsafe impl<G: Garbage> Sync for Bucket<G> {}
+
+impl<G: Garbage> Bucket<G> {
+    /// Creates a new, empty bucket.
+    pub fn new() -> Self {
+        Bucket {
+            head: CachePadded::new(Atomic::null()),
+            epoch: AtomicEpoch::new(Epoch::starting()),
+        }
+    }
+
+    /// Returns `true` if the bucket is observed to be empty.
+    fn is_empty(&self) -> bool {
+        // The pointer is only compared against null, so no guard is needed.
+        self.head.load(Ordering::Relaxed, unsafe { unprotected() }).is_null()
+    }
+
+    /// Returns `true` if the newest bag in the bucket is expired w.r.t. the given global epoch.
+    fn is_expired(&self, global_epoch: Epoch) -> bool {
+        global_epoch.wrapping_sub(self.epoch.load(Ordering::Relaxed)) >= 2
+    }
+
+    /// Pushes a sealed bag into the bucket.
+    pub fn push(&self, sealed_bag: SealedBag<G>, guard: &Guard) {
+        // Published by the release in `push_chain`.
+        self.epoch.store(sealed_bag.epoch, Ordering::Relaxed);
+        let node = Owned::new(BagNode {
+            sealed_bag,
+            next: Atomic::null(),
+        }).into_shared(guard);
+        unsafe { self.push_chain(node, node, guard) }
+    }
+
+    /// Pushes the chain of nodes from `first` to `last` into the bucket.
+    ///
+    /// # Safety
+    ///
+    /// The chain must be owned by the current thread.
+    unsafe fn push_chain(
+        &self,
+        first: Shared<BagNode<G>>,
+        last: Shared<BagNode<G>>,
+        guard: &Guard,
+    ) {
+        let mut head = self.head.load(Ordering::Relaxed, guard);
+        loop {
+            last.deref().next.store(head, Ordering::Relaxed);
+            match self.head.compare_and_set_weak(head, first, Ordering::Release, guard) {
+                Ok(_) => return,
+                Err(err) => head = err.current,
+            }
+        }
+    }
+
+    /// Removes all nodes from the bucket and returns the first one.
+    ///
+    /// The current thread becomes the owner of the chain.
+    fn take<'g>(&self, guard: &'g Guard) -> Shared<'g, BagNode<G>> {
+        self.head.swap(Shared::null(), Ordering::Acquire, guard)
+    }
+
+    /// Removes all nodes from the bucket if they are expired w.r.t. the given global epoch, and
+    /// returns the first one.
+    ///
+    /// The current thread becomes the owner of the chain.
+    fn take_expired<'g>(&self, global_epoch: Epoch, guard: &'g Guard) -> Shared<'g, BagNode<G>> {
+        if self.is_empty() || !self.is_expired(global_epoch) {
+            return Shared::null();
+        }
+
+        let first = self.take(guard);
+
+        // A fresh bag may have been pushed between the check and the swap. The swap synchronizes
+        // with its push, so checking the epoch again covers every bag in the chain.
+        if !first.is_null() && !self.is_expired(global_epoch) {
+            unsafe { self.put_back(first, guard) }
+            return Shared::null();
+        }
+        first
+    }
+
+    /// Pushes the chain of nodes starting at `first` back into the bucket.
+    ///
+    /// # Safety
+    ///
+    /// The chain must be owned by the current thread.
+    unsafe fn put_back(&self, first: Shared<BagNode<G>>, guard: &Guard) {
+        let mut last = first;
+        loop {
+            let next = last.deref().next.load(Ordering::Relaxed, guard);
+            if next.is_null() {
+                break;
+            }
+            last = next;
+        }
+        self.push_chain(first, last, guard);
+    }
+
+    /// Removes all bags from the bucket and passes them to `f`.
+    pub fn take_each<F>(&self, mut f: F)
+    where
+        F: FnMut(SealedBag<G>),
+    {
+        unsafe {
+            let guard = &unprotected();
+            let mut node = self.take(guard);
+            while !node.is_null() {
+                let n = *node.into_owned().into_box();
+                node = n.next.load(Ordering::Relaxed, guard);
+                f(n.sealed_bag);
+            }
+        }
+    }
+
+    /// Destroys all bags in the bucket, executing their deferred functions.
+    pub fn clear(&self) {
+        self.take_each(drop);
+    }
+}
+
+impl<G: Garbage> Drop for Bucket<G> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
 /// The global data for a garbage collector.
 pub struct Global {
-    /// The intrusive linked list of `Local`s.
+    /// The intrusive linked list of `Local`s, unless they are preallocated.
     locals: List<Local>,
 
-    /// The global queue of bags of deferred functions.
-    queue: Queue<SealedBag>,
+    /// The preallocated `Local`s, if the number of participants is fixed.
+    slots: Option<Box<[CachePadded<Local>]>>,
+
+    /// The global buckets of bags of deferred functions, indexed by the epoch of the bags.
+    buckets: [Bucket; BUCKETS],
 
     /// The global epoch.
     pub(crate) epoch: CachePadded<AtomicEpoch>,
+
+    /// The tunable parameters of this garbage collector.
+    pub(crate) config: Config,
+
+    /// Statistics about garbage collection.
+    pub(crate) counters: Counters,
 }
 
 impl Global {
-    /// Number of bags to destroy.
+    /// Default number of bags to destroy.
     const COLLECT_STEPS: usize = 8;
 
     /// Creates a new global data for garbage collection.
     #[inline]
-    pub fn new() -> Self {
+    pub fn new(config: Config) -> Self {
+        let slots = config.participant_capacity.map(|capacity| {
+            (0..capacity)
+                .map(|_| CachePadded::new(Local::new(None, &config)))
+                .collect::<Vec<_>>()
+                .into_boxed_slice()
+        });
+
         Self {
             locals: List::new(),
-            queue: Queue::new(),
+            slots,
+            buckets: [Bucket::new(), Bucket::new(), Bucket::new()],
             epoch: CachePadded::new(AtomicEpoch::new(Epoch::starting())),
+            config,
+            counters: Counters::default(),
+        }
+    }
+
+    /// Returns an iterator over all `Local`s, including free ones.
+    fn locals<'g>(&'g self, guard: &'g Guard) -> Locals<'g> {
+        match self.slots {
+            Some(ref slots) => Locals::Slots(slots.iter()),
+            None => Locals::List(self.locals.iter(guard)),
         }
     }
 
-    /// Pushes the bag into the global queue and replaces the bag with a new empty bag.
+    /// Returns the bucket for bags sealed in `epoch`.
+    #[inline]
+    fn bucket(&self, epoch: Epoch) -> &Bucket {
+        &self.buckets[epoch.position(BUCKETS)]
+    }
+
+    /// Pushes the bag into the global garbage and replaces the bag with a new empty bag.
     pub fn push_bag(&self, bag: &mut Bag, guard: &Guard) {
-        let bag = mem::replace(bag, Bag::new());
+        let bag = mem::replace(bag, Bag::with_capacity(self.config.bag_capacity));
 
         atomic::fence(Ordering::SeqCst);
 
         let epoch = self.epoch.load(Ordering::Relaxed);
-        self.queue.push(bag.seal(epoch), guard);
+        self.counters.sealed_bags.fetch_add(1, Ordering::Relaxed);
+        self.counters.queued_deferreds.fetch_add(bag.len(), Ordering::Relaxed);
+        self.counters.queued_bytes.fetch_add(bag.bytes, Ordering::Relaxed);
+        self.bucket(epoch).push(bag.seal(epoch), guard);
     }
 
-    /// Collects several bags from the global queue and executes deferred functions in them.
+    /// Collects several bags from the global garbage and executes deferred functions in them.
     ///
     /// Note: This may itself produce garbage and in turn allocate new bags.
     ///
@@ -172,24 +512,172 @@ impl Global {
     /// `collect()` is not called.
     #[cold]
     pub fn collect(&self, guard: &Guard) {
+        self.collect_bags(self.config.collect_steps, guard);
+    }
+
+    /// Attempts to advance the global epoch, then collects up to `steps` expired bags from the
+    /// global garbage and executes deferred functions in them.
+    ///
+    /// Returns the number of destroyed bags.
+    pub fn collect_bags(&self, steps: usize, guard: &Guard) -> usize {
         let global_epoch = self.try_advance(guard);
 
-        let steps = if cfg!(feature = "sanitize") {
-            usize::max_value()
-        } else {
-            Self::COLLECT_STEPS
-        };
-
-        for _ in 0..steps {
-            match self.queue.try_pop_if(
-                &|sealed_bag: &SealedBag| sealed_bag.is_expired(global_epoch),
-                guard,
-            )
-            {
-                None => break,
-                Some(sealed_bag) => drop(sealed_bag),
+        let mut collected = 0;
+        for bucket in self.buckets.iter() {
+            collected += self.collect_bucket(bucket, global_epoch, steps - collected, guard).0;
+        }
+        collected
+    }
+
+    /// Collects all expired bags from the global garbage and executes deferred functions in them.
+    ///
+    /// Unlike `collect_bags`, this doesn't attempt to advance the global epoch.
+    ///
+    /// Returns the number of executed deferred functions.
+    pub fn collect_expired(&self, guard: &Guard) -> usize {
+        let global_epoch = self.epoch.load(Ordering::Relaxed);
+
+        self.buckets
+            .iter()
+            .map(|bucket| self.collect_bucket(bucket, global_epoch, usize::max_value(), guard).1)
+            .sum()
+    }
+
+    /// Returns `true` if the global garbage is observed to contain any bags.
+    #[cfg(feature = "use_std")]
+    pub fn has_garbage(&self) -> bool {
+        self.buckets.iter().any(|bucket| !bucket.is_empty())
+    }
+
+    /// Destroys up to `steps` bags from `bucket` if they are expired w.r.t. `global_epoch`, and
+    /// puts the rest back.
+    ///
+    /// Returns the number of destroyed bags and executed deferred functions.
+    fn collect_bucket(
+        &self,
+        bucket: &Bucket,
+        global_epoch: Epoch,
+        steps: usize,
+        guard: &Guard,
+    ) -> (usize, usize) {
+        if steps == 0 {
+            return (0, 0);
+        }
+
+        let mut collected = 0;
+        let mut executed = 0;
+
+        unsafe {
+            let mut node = bucket.take_expired(global_epoch, guard);
+
+            // Put the bags beyond `steps` back before executing any deferred function. They are
+            // destroyed once the bucket is detached again.
+            let mut last = node;
+            for _ in 1..steps {
+                match last.as_ref() {
+                    Some(l) => last = l.next.load(Ordering::Relaxed, guard),
+                    None => break,
+                }
+            }
+            if let Some(l) = last.as_ref() {
+                let rest = l.next.load(Ordering::Relaxed, guard);
+                if !rest.is_null() {
+                    l.next.store(Shared::null(), Ordering::Relaxed);
+                    bucket.put_back(rest, guard);
+                }
+            }
+
+            while let Some(n) = node.as_ref() {
+                let next = n.next.load(Ordering::Relaxed, guard);
+                let bag = &n.sealed_bag.bag;
+                debug_assert!(n.sealed_bag.is_expired(global_epoch));
+
+                self.counters.queued_deferreds.fetch_sub(bag.len(), Ordering::Relaxed);
+                self.counters.queued_bytes.fetch_sub(bag.bytes, Ordering::Relaxed);
+                self.counters.collected_bags.fetch_add(1, Ordering::Relaxed);
+                collected += 1;
+                executed += bag.len();
+
+                drop(node.into_owned());
+                node = next;
             }
         }
+        (collected, executed)
+    }
+
+    /// Returns `true` if the garbage limit is configured and exceeded.
+    #[inline]
+    pub fn is_over_limit(&self) -> bool {
+        match self.config.garbage_limit {
+            None => false,
+            Some(limit) => limit.is_exceeded(&self.counters.snapshot()),
+        }
+    }
+
+    /// Returns the participants that have been blocking the global epoch for at least
+    /// `threshold`.
+    #[cfg(feature = "use_std")]
+    pub fn stalled_participants(
+        &self,
+        threshold: Duration,
+        guard: &Guard,
+    ) -> Vec<StalledParticipant> {
+        let global_epoch = self.epoch.load(Ordering::Relaxed);
+        atomic::fence(Ordering::SeqCst);
+
+        let mut stalled = Vec::new();
+        for local in self.locals(guard) {
+            match local {
+                Err(IterError::Stalled) => {
+                    // The iteration restarts from the head of the list.
+                    stalled.clear();
+                }
+                Ok(local) => {
+                    // Pinning the guard may have advanced the global epoch, so the participant
+                    // the guard belongs to may appear stalled.
+                    if ptr::eq(local, guard.local) {
+                        continue;
+                    }
+
+                    let local_epoch = local.epoch.load(Ordering::Relaxed);
+
+                    if local_epoch.is_pinned() && local_epoch.unpinned() != global_epoch {
+                        let blocking_for =
+                            local.observe_stall(&self.config, local_epoch, global_epoch);
+                        if blocking_for >= threshold {
+                            stalled.push(local.stalled_participant(
+                                local_epoch,
+                                global_epoch,
+                                blocking_for,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        stalled
+    }
+
+    /// Returns `true` if the current thread has a pinned participant.
+    ///
+    /// Under loom, all threads of a model share the same thread ID, so this always returns
+    /// `false`.
+    #[cfg(feature = "use_std")]
+    fn is_pinned_by_current_thread(&self) -> bool {
+        #[cfg(not(crossbeam_loom))]
+        {
+            let thread_id = thread::current().id();
+
+            // `Local`s are only deallocated along with the `Global`, so it is safe to iterate them
+            // with `unprotected`.
+            unsafe { self.locals(unprotected()) }
+                .flatten()
+                .any(|local| local.is_pinned_by(thread_id))
+        }
+        #[cfg(crossbeam_loom)]
+        {
+            false
+        }
     }
 
     /// Attempts to advance the global epoch.
@@ -203,12 +691,20 @@ impl Global {
     #[cold]
     pub fn try_advance(&self, guard: &Guard) -> Epoch {
         let global_epoch = self.epoch.load(Ordering::Relaxed);
-        atomic::fence(Ordering::SeqCst);
+
+        // Pairs with the fence in `Local::pin`. If pinning only executes a compiler fence, this
+        // barrier executes a full fence on the pinning threads instead.
+        if self.config.membarrier {
+            membarrier::barrier();
+        } else {
+            atomic::fence(Ordering::SeqCst);
+        }
 
         // TODO(stjepang): `Local`s are stored in a linked list because linked lists are fairly
         // easy to implement in a lock-free manner. However, traversal can be slow due to cache
-        // misses and data dependencies. We should experiment with other data structures as well.
-        for local in self.locals.iter(&guard) {
+        // misses and data dependencies. Collectors with a fixed number of participants store them
+        // in an array instead, which never stalls the iteration.
+        for local in self.locals(guard) {
             match local {
                 Err(IterError::Stalled) => {
                     // A concurrent thread stalled this iteration. That thread might also try to
@@ -222,6 +718,9 @@ impl Global {
                     // If the participant was pinned in a different epoch, we cannot advance the
                     // global epoch just yet.
                     if local_epoch.is_pinned() && local_epoch.unpinned() != global_epoch {
+                        self.counters.blocked_advances.fetch_add(1, Ordering::Relaxed);
+                        #[cfg(feature = "use_std")]
+                        local.observe_stall(&self.config, local_epoch, global_epoch);
                         return global_epoch;
                     }
                 }
@@ -238,10 +737,40 @@ impl Global {
         // advanced two steps ahead of it.
         let new_epoch = global_epoch.successor();
         self.epoch.store(new_epoch, Ordering::Release);
+        self.counters.epoch_advances.fetch_add(1, Ordering::Relaxed);
         new_epoch
     }
 }
 
+#[cfg(feature = "debug-reclaim")]
+impl Drop for Global {
+    fn drop(&mut self) {
+        // Execute the remaining deferred functions before looking for retired pointers that were
+        // never freed.
+        for bucket in self.buckets.iter() {
+            bucket.clear();
+        }
+        ::debug::report_leaks(self as *const Global as usize);
+    }
+}
+
+/// An iterator over the `Local`s of a `Global`.
+enum Locals<'g> {
+    List(Iter<'g, Local, Local>),
+    Slots(slice::Iter<'g, CachePadded<Local>>),
+}
+
+impl<'g> Iterator for Locals<'g> {
+    type Item = Result<&'g Local, IterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match *self {
+            Locals::List(ref mut iter) => iter.next(),
+            Locals::Slots(ref mut iter) => iter.next().map(|local| Ok(&**local)),
+        }
+    }
+}
+
 /// Participant for garbage collection.
 pub struct Local {
     /// A node in the intrusive linked list of `Local`s.
@@ -250,10 +779,13 @@ pub struct Local {
     /// The local epoch.
     epoch: AtomicEpoch,
 
-    /// A reference to the global data.
+    /// Whether this `Local` is used by a participant. Free `Local`s are reused by `register`.
+    in_use: AtomicBool,
+
+    /// A reference to the global data, present while this `Local` is in use.
     ///
     /// When all guards and handles get dropped, this reference is destroyed.
-    collector: UnsafeCell<ManuallyDrop<Collector>>,
+    collector: UnsafeCell<Option<Collector>>,
 
     /// The local bag of deferred functions.
     pub(crate) bag: UnsafeCell<Bag>,
@@ -268,32 +800,86 @@ pub struct Local {
     ///
     /// This is just an auxilliary counter that sometimes kicks off collection.
     pin_count: Cell<Wrapping<usize>>,
+
+    /// The thread using this participant and the stall it is causing, if any.
+    #[cfg(feature = "use_std")]
+    stall: StallInfo,
 }
 
 impl Local {
-    /// Number of pinnings after which a participant will execute some deferred functions from the
-    /// global queue.
+    /// Default number of pinnings after which a participant will execute some deferred functions
+    /// from the global queue.
     const PINNINGS_BETWEEN_COLLECT: usize = 128;
 
-    /// Registers a new `Local` in the provided `Global`.
-    pub fn register(collector: &Collector) -> LocalHandle {
+    /// Creates a new `Local`, used by a participant of `collector` if there is one and free
+    /// otherwise.
+    fn new(collector: Option<Collector>, config: &Config) -> Local {
+        let in_use = collector.is_some();
+
+        Local {
+            entry: Entry::default(),
+            epoch: AtomicEpoch::new(Epoch::starting()),
+            in_use: AtomicBool::new(in_use),
+            collector: UnsafeCell::new(collector),
+            bag: UnsafeCell::new(Bag::with_capacity(config.bag_capacity)),
+            guard_count: Cell::new(0),
+            handle_count: Cell::new(in_use as usize),
+            pin_count: Cell::new(Wrapping(0)),
+            #[cfg(feature = "use_std")]
+            stall: StallInfo::new(),
+        }
+    }
+
+    /// Registers a new participant in the provided `Global`, reusing a free `Local` if there is
+    /// one.
+    ///
+    /// If the `Local`s are preallocated and none of them is free, an error is returned.
+    pub fn register(collector: &Collector) -> Result<LocalHandle, TryRegisterError> {
         unsafe {
+            // `Local`s are only deallocated along with the `Global`, so it is safe to iterate them
+            // with `unprotected`. Since no entry is ever deleted, iteration never stalls.
+            for local in collector.global.locals(&unprotected()).flatten() {
+                if local.acquire_free(collector) {
+                    return Ok(LocalHandle { local });
+                }
+            }
+
+            if collector.global.slots.is_some() {
+                return Err(TryRegisterError::new());
+            }
+
             // Since we dereference no pointers in this block, it is safe to use `unprotected`.
 
-            let local = Owned::new(Local {
-                entry: Entry::default(),
-                epoch: AtomicEpoch::new(Epoch::starting()),
-                collector: UnsafeCell::new(ManuallyDrop::new(collector.clone())),
-                bag: UnsafeCell::new(Bag::new()),
-                guard_count: Cell::new(0),
-                handle_count: Cell::new(1),
-                pin_count: Cell::new(Wrapping(0)),
-            }).into_shared(&unprotected());
+            let local = Owned::new(Local::new(Some(collector.clone()), &collector.global.config))
+                .into_shared(&unprotected());
             collector.global.locals.insert(local, &unprotected());
-            LocalHandle { local: local.as_raw() }
+            collector.global.counters.participants.fetch_add(1, Ordering::Relaxed);
+            Ok(LocalHandle { local: local.as_raw() })
         }
     }
 
+    /// Attempts to take this `Local` for a new participant of `collector`, if it is free.
+    fn acquire_free(&self, collector: &Collector) -> bool {
+        // Pairs with the release in `finalize`, making the previous participant's accesses to the
+        // thread-local fields happen before ours.
+        if self
+            .in_use
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+
+        // The local bag was emptied and the local epoch unpinned by `finalize`.
+        self.collector.with_mut(|c| unsafe { *c = Some(collector.clone()) });
+        self.handle_count.set(1);
+        self.pin_count.set(Wrapping(0));
+        #[cfg(feature = "use_std")]
+        self.stall.reset();
+        collector.global.counters.participants.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
     /// Returns a reference to the `Global` in which this `Local` resides.
     #[inline]
     pub fn global(&self) -> &Global {
@@ -303,7 +889,13 @@ impl Local {
     /// Returns a reference to the `Collector` in which this `Local` resides.
     #[inline]
     pub fn collector(&self) -> &Collector {
-        unsafe { &**self.collector.get() }
+        self.collector.with(|c| unsafe {
+            match *c {
+                Some(ref collector) => collector,
+                // Only the participant using this `Local` accesses its collector.
+                None => hint::unreachable_unchecked(),
+            }
+        })
     }
 
     /// Returns `true` if the current participant is pinned.
@@ -312,28 +904,119 @@ impl Local {
         self.guard_count.get() > 0
     }
 
-    /// Adds `deferred` to the thread-local bag.
+    /// Returns `true` if this participant belongs to the thread with the given ID and is pinned.
+    ///
+    /// This may be called by any thread.
+    #[cfg(all(feature = "use_std", not(crossbeam_loom)))]
+    fn is_pinned_by(&self, thread_id: ThreadId) -> bool {
+        // The epoch is loaded while the participant can't be taken over, so it is not mistaken for
+        // the epoch of a new participant on another thread.
+        self.stall.with_thread_id(|id| {
+            id == Some(thread_id) && self.epoch.load(Ordering::Relaxed).is_pinned()
+        })
+    }
+
+    /// Records that this participant, pinned in `local_epoch`, is blocking `global_epoch` from
+    /// advancing, and calls the stall callback if the stall has just crossed its threshold.
+    ///
+    /// Returns for how long the participant has been blocking.
+    ///
+    /// This is called by other threads, which must not access the reference to the `Global`
+    /// stored in this `Local` as it may be taken over by a new participant at any time.
+    #[cfg(feature = "use_std")]
+    #[cold]
+    fn observe_stall(&self, config: &Config, local_epoch: Epoch, global_epoch: Epoch) -> Duration {
+        let threshold = config.stall_callback.map(|(threshold, _)| threshold);
+        let (blocking_for, report) = self.stall.observe(local_epoch, threshold);
+
+        if report {
+            if let Some((_, callback)) = config.stall_callback {
+                callback(&self.stalled_participant(local_epoch, global_epoch, blocking_for));
+            }
+        }
+        blocking_for
+    }
+
+    /// Returns a report about this participant stalled in `local_epoch`.
+    #[cfg(feature = "use_std")]
+    fn stalled_participant(
+        &self,
+        local_epoch: Epoch,
+        global_epoch: Epoch,
+        blocking_for: Duration,
+    ) -> StalledParticipant {
+        let epoch_lag = global_epoch.wrapping_sub(local_epoch) as usize;
+        self.stall.participant(epoch_lag, blocking_for)
+    }
+
+    /// Adds `deferred` to the thread-local bag, accounting `size` bytes to it.
+    ///
+    /// The garbage limit is not enforced here; see `enforce_garbage_limit`.
     ///
     /// # Safety
     ///
     /// It should be safe for another thread to execute the given function.
-    pub unsafe fn defer(&self, mut deferred: Deferred, guard: &Guard) {
-        let bag = &mut *self.bag.get();
+    pub unsafe fn defer(&self, mut deferred: Deferred, size: usize, guard: &Guard) {
+        let bag = self.bag.with_mut(|b| &mut *b);
 
         while let Err(d) = bag.try_push(deferred) {
             self.global().push_bag(bag, guard);
             deferred = d;
         }
+        bag.bytes += size;
+    }
+
+    /// Applies the garbage policy if the garbage limit is exceeded.
+    #[inline]
+    pub fn enforce_garbage_limit(&self, guard: &Guard) {
+        if self.global().is_over_limit() {
+            self.apply_garbage_policy(guard);
+        }
+    }
+
+    /// Applies the garbage policy.
+    ///
+    /// With `GarbagePolicy::Block`, this waits until the global epoch advances past the epoch this
+    /// participant is pinned in. Since the participant stays pinned, the global epoch can't advance
+    /// any further, so waiting for more would be a deadlock.
+    #[cold]
+    fn apply_garbage_policy(&self, guard: &Guard) {
+        let global = self.global();
+
+        match global.config.garbage_policy {
+            GarbagePolicy::Callback(f) => f(&global.counters.snapshot()),
+            GarbagePolicy::Block => {
+                let local_epoch = self.epoch.load(Ordering::Relaxed).unpinned();
+
+                while global.is_over_limit() {
+                    let global_epoch = if global.config.collect_on_pin {
+                        global.collect(guard);
+                        global.epoch.load(Ordering::Relaxed)
+                    } else {
+                        global.try_advance(guard)
+                    };
+
+                    if global_epoch != local_epoch {
+                        break;
+                    }
+
+                    #[cfg(feature = "use_std")]
+                    ::primitive::thread::yield_now();
+                }
+            }
+        }
     }
 
     pub fn flush(&self, guard: &Guard) {
-        let bag = unsafe { &mut *self.bag.get() };
+        let bag = self.bag.with_mut(|b| unsafe { &mut *b });
 
         if !bag.is_empty() {
             self.global().push_bag(bag, guard);
         }
 
-        self.global().collect(guard);
+        if self.global().config.collect_on_pin {
+            self.global().collect(guard);
+        }
     }
 
     /// Pins the `Local`.
@@ -351,7 +1034,12 @@ impl Local {
             // Now we must store `new_epoch` into `self.epoch` and execute a `SeqCst` fence.
             // The fence makes sure that any future loads from `Atomic`s will not happen before
             // this store.
-            if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+            if self.global().config.membarrier {
+                // `Global::try_advance` executes a full fence on this thread on our behalf, so
+                // only the compiler must be prevented from reordering the store and the loads.
+                self.epoch.store(new_epoch, Ordering::Relaxed);
+                membarrier::light_barrier();
+            } else if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
                 // HACK(stjepang): On x86 architectures there are two different ways of executing
                 // a `SeqCst` fence.
                 //
@@ -373,16 +1061,34 @@ impl Local {
             let count = self.pin_count.get();
             self.pin_count.set(count + Wrapping(1));
 
-            // After every `PINNINGS_BETWEEN_COLLECT` try advancing the epoch and collecting
+            // After every `pinnings_between_collect` try advancing the epoch and collecting
             // some garbage.
-            if count.0 % Self::PINNINGS_BETWEEN_COLLECT == 0 {
-                self.global().collect(&guard);
+            let config = &self.global().config;
+            if count.0 % config.pinnings_between_collect == 0 {
+                // The thread is looked up on the first pinning rather than on registration,
+                // which is more frequent and may happen while the thread is exiting.
+                #[cfg(feature = "use_std")]
+                {
+                    if count.0 == 0 {
+                        self.stall.record_thread();
+                    }
+                }
+
+                if config.collect_on_pin {
+                    self.global().collect(&guard);
+                }
             }
         }
 
         guard
     }
 
+    /// Prevents the thread using this participant from being looked up, because it is exiting.
+    #[cfg(feature = "use_std")]
+    pub fn forget_thread(&self) {
+        self.stall.forget_thread();
+    }
+
     /// Unpins the `Local`.
     #[inline]
     pub fn unpin(&self) {
@@ -398,6 +1104,46 @@ impl Local {
         }
     }
 
+    /// Blocks until every participant that was pinned at the time of the call gets unpinned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this participant or another participant of the current thread is pinned, since
+    /// it would wait for itself forever. Other participants are only checked with the `use_std`
+    /// feature.
+    #[cold]
+    pub fn synchronize(&self) {
+        assert!(!self.is_pinned(), "`synchronize` called while pinned");
+        #[cfg(feature = "use_std")]
+        assert!(
+            !self.global().is_pinned_by_current_thread(),
+            "`synchronize` called while the current thread is pinned"
+        );
+
+        // The fence orders all memory accesses before this call (typically unlinking objects from
+        // data structures) before the load of the global epoch.
+        atomic::fence(Ordering::SeqCst);
+        let start = self.global().epoch.load(Ordering::Relaxed);
+
+        // Participants pinned at this point are pinned in `start` or the epoch before it. The
+        // global epoch can advance from `start` only when none of them is pinned in the previous
+        // epoch, and once more only when none of them is pinned in `start`. Therefore, after two
+        // advancements all of them must have been unpinned at least once.
+        loop {
+            let epoch = {
+                let guard = &self.pin();
+                self.global().try_advance(guard)
+            };
+
+            if epoch.wrapping_sub(start) >= 2 {
+                break;
+            }
+
+            #[cfg(feature = "use_std")]
+            ::primitive::thread::yield_now();
+        }
+    }
+
     /// Unpins and then pins the `Local`.
     #[inline]
     pub fn repin(&self) {
@@ -406,7 +1152,7 @@ impl Local {
         // Update the local epoch only if there's only one guard.
         if guard_count == 1 {
             let epoch = self.epoch.load(Ordering::Relaxed);
-            let global_epoch = self.global().epoch.load(Ordering::Relaxed);
+            let global_epoch = self.global().epoch.load(Ordering::Relaxed).pinned();
 
             // Update the local epoch only if the global epoch is greater than the local epoch.
             if epoch != global_epoch {
@@ -421,6 +1167,23 @@ impl Local {
         }
     }
 
+    /// Announces a quiescent state of a participant that stays pinned through `guard`.
+    ///
+    /// This repins the `Local` and, once every `pinnings_between_collect` calls, collects some
+    /// garbage, just like `pin` does for participants that unpin in between.
+    #[inline]
+    pub fn quiesce(&self, guard: &Guard) {
+        self.repin();
+
+        let count = self.pin_count.get();
+        self.pin_count.set(count + Wrapping(1));
+
+        let config = &self.global().config;
+        if count.0 % config.pinnings_between_collect == 0 && config.collect_on_pin {
+            self.global().collect(guard);
+        }
+    }
+
     /// Increments the handle count.
     #[inline]
     pub fn acquire_handle(&self) {
@@ -442,12 +1205,16 @@ impl Local {
         }
     }
 
-    /// Removes the `Local` from the global linked list.
+    /// Unregisters the participant, leaving the `Local` free for reuse.
     #[cold]
     fn finalize(&self) {
         debug_assert_eq!(self.guard_count.get(), 0);
         debug_assert_eq!(self.handle_count.get(), 0);
 
+        // The thread may be exiting, so it must not be looked up by the following call to `pin`.
+        #[cfg(feature = "use_std")]
+        self.stall.forget_thread();
+
         // Temporarily increment handle count. This is required so that the following call to `pin`
         // doesn't call `finalize` again.
         self.handle_count.set(1);
@@ -455,19 +1222,23 @@ impl Local {
             // Pin and move the local bag into the global queue. It's important that `push_bag`
             // doesn't defer destruction on any new garbage.
             let guard = &self.pin();
-            self.global().push_bag(&mut *self.bag.get(), guard);
+            self.global().push_bag(self.bag.with_mut(|b| &mut *b), guard);
         }
         // Revert the handle count back to zero.
         self.handle_count.set(0);
 
         unsafe {
-            // Take the reference to the `Global` out of this `Local`. Since we're not protected
-            // by a guard at this time, it's crucial that the reference is read before marking the
-            // `Local` as deleted.
-            let collector: Collector = ptr::read(&*(*self.collector.get()));
+            // Take the reference to the `Global` out of this `Local`. It's crucial that the
+            // reference is read before marking the `Local` as free, since another participant may
+            // take it over right after.
+            let collector = self.collector.with_mut(|c| (*c).take()).unwrap();
+
+            // Mark this `Local` as free. From now on, it must not be accessed by this thread.
+            self.in_use.store(false, Ordering::Release);
 
-            // Mark this node in the linked list as deleted.
-            self.entry.delete(&unprotected());
+            // Pairs with the load in `Collector::shutdown`, which needs to see the bag pushed
+            // above once it sees this participant unregistered.
+            collector.global.counters.participants.fetch_sub(1, Ordering::Release);
 
             // Finally, drop the reference to the global. Note that this might be the last reference
             // to the `Global`. If so, the global data will be destroyed and all deferred functions
@@ -477,30 +1248,15 @@ impl Local {
     }
 }
 
-impl IsElement<Local> for Local {
-    fn entry_of(local: &Local) -> &Entry {
-        let entry_ptr = (local as *const Local as usize + offset_of!(Local, entry)) as *const Entry;
-        unsafe { &*entry_ptr }
-    }
-
-    unsafe fn element_of(entry: &Entry) -> &Local {
-        // offset_of! macro uses unsafe, but it's unnecessary in this context.
-        #[allow(unused_unsafe)]
-        let local_ptr = (entry as *const Entry as usize - offset_of!(Local, entry)) as *const Local;
-        &*local_ptr
-    }
-
-    unsafe fn finalize(entry: &Entry) {
-        let local = Self::element_of(entry);
-        drop(Box::from_raw(local as *const Local as *mut Local));
-    }
-}
+impl_is_element!(Local, entry);
 
 #[cfg(test)]
 mod tests {
     use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
     use std::sync::atomic::Ordering;
 
+    use crossbeam_utils::thread;
+
     use super::*;
 
     #[test]
@@ -540,4 +1296,129 @@ mod tests {
         drop(bag);
         assert_eq!(FLAG.load(Ordering::Relaxed), MAX_OBJECTS);
     }
+
+    #[test]
+    fn keep_unexpired_bags() {
+        static FLAG: AtomicUsize = ATOMIC_USIZE_INIT;
+        fn incr() {
+            FLAG.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let global = Global::new(Config::default());
+        let guard = unsafe { &unprotected() };
+
+        // Bags sealed three epochs apart go into the same bucket.
+        let first = Epoch::starting();
+        let second = first.successor().successor().successor();
+        for &epoch in &[first, second] {
+            global.epoch.store(epoch, Ordering::Relaxed);
+            let mut bag = Bag::new();
+            assert!(unsafe { bag.try_push(Deferred::new(incr)).is_ok() });
+            global.push_bag(&mut bag, guard);
+        }
+        let bucket = global.bucket(first);
+
+        // The second bag is not expired, so neither bag is destroyed.
+        assert_eq!(global.collect_bucket(bucket, second, usize::max_value(), guard), (0, 0));
+        assert_eq!(FLAG.load(Ordering::Relaxed), 0);
+        assert!(!bucket.is_empty());
+
+        let third = second.successor().successor();
+        assert_eq!(global.collect_bucket(bucket, third, usize::max_value(), guard), (2, 2));
+        assert_eq!(FLAG.load(Ordering::Relaxed), 2);
+        assert!(bucket.is_empty());
+        assert_eq!(global.counters.snapshot().queued_deferreds(), 0);
+    }
+
+    #[test]
+    fn collect_all_buckets() {
+        static FLAG: AtomicUsize = ATOMIC_USIZE_INIT;
+        fn incr() {
+            FLAG.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let global = Global::new(Config::default());
+        let guard = unsafe { &unprotected() };
+
+        // Fill every bucket, as if no garbage had been collected for a while.
+        let first = Epoch::starting();
+        let mut epoch = first;
+        for _ in 0..BUCKETS {
+            global.epoch.store(epoch, Ordering::Relaxed);
+            let mut bag = Bag::new();
+            assert!(unsafe { bag.try_push(Deferred::new(incr)).is_ok() });
+            global.push_bag(&mut bag, guard);
+            epoch = epoch.successor();
+        }
+
+        // Only the bag sealed in the previous epoch is not expired.
+        global.epoch.store(epoch, Ordering::Relaxed);
+        assert_eq!(global.collect_expired(guard), 2);
+        assert_eq!(FLAG.load(Ordering::Relaxed), 2);
+        assert!(global.bucket(first).is_empty());
+        assert!(!global.bucket(epoch.successor().successor()).is_empty());
+    }
+
+    #[test]
+    fn collect_steps() {
+        static FLAG: AtomicUsize = ATOMIC_USIZE_INIT;
+        fn incr() {
+            FLAG.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let global = Global::new(Config::default());
+        let guard = unsafe { &unprotected() };
+
+        let epoch = Epoch::starting();
+        for _ in 0..5 {
+            let mut bag = Bag::new();
+            assert!(unsafe { bag.try_push(Deferred::new(incr)).is_ok() });
+            global.push_bag(&mut bag, guard);
+        }
+        let bucket = global.bucket(epoch);
+
+        // The bags beyond the limit are put back and destroyed by later collections.
+        let expired = epoch.successor().successor();
+        assert_eq!(global.collect_bucket(bucket, expired, 2, guard), (2, 2));
+        assert_eq!(global.collect_bucket(bucket, expired, 2, guard), (2, 2));
+        assert_eq!(global.collect_bucket(bucket, expired, 2, guard), (1, 1));
+        assert_eq!(FLAG.load(Ordering::Relaxed), 5);
+        assert!(bucket.is_empty());
+    }
+
+    #[test]
+    fn recycle_locals() {
+        const THREADS: usize = 4;
+
+        static DESTROYS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        let collector = Collector::new();
+        let locals = || unsafe { collector.global.locals.iter(&unprotected()).count() };
+
+        for _ in 0..10 {
+            drop(collector.register());
+        }
+        assert_eq!(locals(), 1);
+
+        let handles: Vec<LocalHandle> = (0..3).map(|_| collector.register()).collect();
+        assert_eq!(locals(), 3);
+        drop(handles);
+
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        let handle = collector.register();
+                        let guard = handle.pin();
+                        guard.defer(|| DESTROYS.fetch_add(1, Ordering::Relaxed));
+                    }
+                });
+            }
+        });
+        assert!(locals() <= THREADS);
+        assert_eq!(collector.stats().participants(), 0);
+
+        drop(collector);
+        assert_eq!(DESTROYS.load(Ordering::Relaxed), THREADS * 100);
+    }
 }
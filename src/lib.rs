@@ -72,28 +72,60 @@ extern crate alloc;
 #[cfg(not(feature = "nightly"))]
 extern crate std as alloc;
 
-extern crate arrayvec;
 extern crate crossbeam_utils;
 #[cfg(feature = "use_std")]
+#[cfg_attr(crossbeam_loom, allow(unused_imports))]
 #[macro_use]
 extern crate lazy_static;
-#[macro_use]
+#[cfg(all(target_os = "linux", feature = "membarrier"))]
+extern crate libc;
+#[cfg(crossbeam_loom)]
+extern crate loom;
+#[cfg(all(crossbeam_loom, not(feature = "loom")))]
+compile_error!("model checking with `--cfg crossbeam_loom` requires the `loom` feature");
 extern crate memoffset;
 #[macro_use]
 extern crate scopeguard;
 
+#[macro_use]
+mod macros;
+
 mod atomic;
 mod collector;
+#[cfg(feature = "debug-reclaim")]
+mod debug;
 #[cfg(feature = "use_std")]
 mod default;
 mod deferred;
 mod epoch;
 mod guard;
+pub mod hazard;
 mod internal;
-mod sync;
+mod limit;
+mod membarrier;
+mod primitive;
+mod qsbr;
+mod reclaim;
+#[cfg(feature = "use_std")]
+mod reclaimer;
+#[cfg(feature = "use_std")]
+mod stall;
+mod stats;
+pub mod sync;
+mod versioned;
 
-pub use self::atomic::{Atomic, CompareAndSetError, CompareAndSetOrdering, Owned, Shared, Pointer};
+pub use self::atomic::{Atomic, CompareAndSetError, CompareAndSetOrdering, Owned, Pointable, Pointer,
+                       Shared};
 pub use self::guard::{unprotected, Guard};
 #[cfg(feature = "use_std")]
-pub use self::default::{default_collector, is_pinned, pin};
-pub use self::collector::{Collector, LocalHandle};
+pub use self::default::{default_collector, is_pinned, pin, synchronize};
+pub use self::collector::{Collector, CollectorBuilder, LocalHandle, TryRegisterError};
+#[cfg(feature = "use_std")]
+pub use self::reclaimer::Reclaimer;
+pub use self::limit::{GarbageLimit, GarbagePolicy, TryDeferError};
+pub use self::qsbr::QsbrHandle;
+pub use self::reclaim::{Protect, Reclaim, Retire};
+#[cfg(feature = "use_std")]
+pub use self::stall::StalledParticipant;
+pub use self::stats::CollectorStats;
+pub use self::versioned::{AtomicVersioned, Versioned, VersionedCompareAndSetError};
@@ -0,0 +1,111 @@
+//! Bounding the amount of outstanding garbage.
+//!
+//! If a participant stays pinned for a long time, the global epoch cannot advance and sealed bags
+//! accumulate in the global queue. A collector can be configured with a [`GarbageLimit`] and a
+//! [`GarbagePolicy`] that decides what happens to threads deferring new garbage while the limit
+//! is exceeded.
+//!
+//! [`GarbageLimit`]: enum.GarbageLimit.html
+//! [`GarbagePolicy`]: enum.GarbagePolicy.html
+
+use core::fmt;
+#[cfg(feature = "use_std")]
+use std::error;
+
+use stats::CollectorStats;
+
+/// A limit on the amount of garbage waiting in the global queue of a collector.
+///
+/// Only garbage in sealed bags is accounted for, so the actual amount of outstanding garbage may
+/// exceed the limit by the contents of the thread-local bags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GarbageLimit {
+    /// Limits the number of queued deferred functions.
+    Deferreds(usize),
+
+    /// Limits the number of queued bytes.
+    ///
+    /// The size of a deferred function is the size of its closure, and the size of an object
+    /// passed to [`Guard::defer_destroy`] is the size of the object itself. Heap memory owned by
+    /// the object is not accounted for.
+    ///
+    /// [`Guard::defer_destroy`]: struct.Guard.html#method.defer_destroy
+    Bytes(usize),
+}
+
+impl GarbageLimit {
+    /// Returns `true` if the limit is exceeded according to `stats`.
+    pub(crate) fn is_exceeded(&self, stats: &CollectorStats) -> bool {
+        match *self {
+            GarbageLimit::Deferreds(limit) => stats.queued_deferreds() > limit,
+            GarbageLimit::Bytes(limit) => stats.queued_bytes() > limit,
+        }
+    }
+}
+
+/// What happens when a thread defers a function while the [`GarbageLimit`] is exceeded.
+///
+/// The policy applies to [`Guard::defer`], [`Guard::defer_unchecked`] and
+/// [`Guard::defer_destroy`]. [`Guard::try_defer`] never applies the policy and fails instead.
+///
+/// [`GarbageLimit`]: enum.GarbageLimit.html
+/// [`Guard::defer`]: struct.Guard.html#method.defer
+/// [`Guard::defer_unchecked`]: struct.Guard.html#method.defer_unchecked
+/// [`Guard::defer_destroy`]: struct.Guard.html#method.defer_destroy
+/// [`Guard::try_defer`]: struct.Guard.html#method.try_defer
+#[derive(Clone, Copy, Debug)]
+pub enum GarbagePolicy {
+    /// Blocks the deferring thread until the global epoch advances past the epoch it is pinned
+    /// in, collecting garbage in the meantime.
+    ///
+    /// This is as far as the global epoch can advance while the thread is pinned, so the thread
+    /// never waits for itself. If the global epoch is already ahead, the thread doesn't block.
+    Block,
+
+    /// Calls the given function with a snapshot of the collector's statistics and then defers
+    /// the function as usual.
+    ///
+    /// The callback is invoked on every deferral while the limit is exceeded.
+    Callback(fn(&CollectorStats)),
+}
+
+/// The error returned by [`Guard::try_defer`] when the [`GarbageLimit`] is exceeded.
+///
+/// The rejected function can be recovered with [`into_inner`].
+///
+/// [`Guard::try_defer`]: struct.Guard.html#method.try_defer
+/// [`GarbageLimit`]: enum.GarbageLimit.html
+/// [`into_inner`]: struct.TryDeferError.html#method.into_inner
+pub struct TryDeferError<F> {
+    f: F,
+}
+
+impl<F> TryDeferError<F> {
+    pub(crate) fn new(f: F) -> Self {
+        TryDeferError { f }
+    }
+
+    /// Returns the function that could not be deferred.
+    pub fn into_inner(self) -> F {
+        self.f
+    }
+}
+
+impl<F> fmt::Debug for TryDeferError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TryDeferError").finish()
+    }
+}
+
+impl<F> fmt::Display for TryDeferError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "garbage limit exceeded")
+    }
+}
+
+#[cfg(feature = "use_std")]
+impl<F> error::Error for TryDeferError<F> {
+    fn description(&self) -> &str {
+        "garbage limit exceeded"
+    }
+}
@@ -0,0 +1,62 @@
+/// Implements [`IsElement`] for a struct that embeds an [`Entry`] in one of its fields.
+///
+/// `impl_is_element!(T, field)` implements `IsElement<T>` for `T` itself, so that `T` can be put
+/// into a `List<T>`. `impl_is_element!(C => T, field)` implements `IsElement<T>` for a separate
+/// type `C`, which makes it possible to put `T` into several lists, each using a different field.
+///
+/// The generated `finalize` deallocates the element as an `Owned<T>`, so elements must be
+/// allocated with `Owned::new` (or `Box::new`).
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate crossbeam_epoch as epoch;
+///
+/// use epoch::sync::list::{Entry, List};
+///
+/// struct Node {
+///     by_id: Entry,
+///     by_name: Entry,
+///     id: usize,
+///     name: String,
+/// }
+///
+/// struct ById;
+/// struct ByName;
+///
+/// impl_is_element!(ById => Node, by_id);
+/// impl_is_element!(ByName => Node, by_name);
+///
+/// fn main() {
+///     let by_id: List<Node, ById> = List::new();
+///     let by_name: List<Node, ByName> = List::new();
+/// #   drop((by_id, by_name));
+/// }
+/// ```
+///
+/// [`IsElement`]: sync/list/trait.IsElement.html
+/// [`Entry`]: sync/list/struct.Entry.html
+#[macro_export]
+macro_rules! impl_is_element {
+    ($elem:path, $field:ident) => {
+        impl_is_element!($elem => $elem, $field);
+    };
+    ($helper:ty => $elem:path, $field:ident) => {
+        unsafe impl $crate::sync::list::IsElement<$elem> for $helper {
+            fn entry_of(element: &$elem) -> &$crate::sync::list::Entry {
+                &element.$field
+            }
+
+            unsafe fn element_of(entry: &$crate::sync::list::Entry) -> &$elem {
+                let offset = $crate::sync::list::__offset_of!($elem, $field);
+                &*((entry as *const $crate::sync::list::Entry as usize - offset) as *const $elem)
+            }
+
+            unsafe fn finalize(entry: &$crate::sync::list::Entry) {
+                let element = <Self as $crate::sync::list::IsElement<$elem>>::element_of(entry);
+                drop($crate::Owned::from_raw(element as *const $elem as *mut $elem));
+            }
+        }
+    };
+}
@@ -0,0 +1,128 @@
+//! Asymmetric fences based on the Linux `membarrier` system call.
+//!
+//! Pinning a participant must be followed by a `SeqCst` fence, so that the participant's epoch is
+//! visible to threads advancing the global epoch before it loads any pointers. This fence is
+//! executed on every pinning, while the global epoch is advanced only occasionally.
+//!
+//! `membarrier(MEMBARRIER_CMD_PRIVATE_EXPEDITED)` executes a full memory barrier on every CPU
+//! currently running a thread of the process. If the thread advancing the global epoch issues it
+//! right before reading the participants' epochs, pinning only needs to prevent the compiler from
+//! reordering its accesses, which makes the fast path considerably cheaper at the expense of a
+//! system call in `Global::try_advance`.
+//!
+//! The system call is available since Linux 4.14. On other platforms, older kernels, without the
+//! `membarrier` feature, or under loom, `is_supported` returns `false` and `barrier` falls back to
+//! a `SeqCst` fence.
+
+use core::sync::atomic::Ordering;
+
+use primitive::sync::atomic;
+
+/// Returns `true` if the current process can use asymmetric fences.
+///
+/// The first call registers the process for expedited private barriers.
+#[inline]
+pub fn is_supported() -> bool {
+    imp::is_supported()
+}
+
+/// Executes the heavy side of an asymmetric fence.
+///
+/// Only call this after `is_supported` returned `true`, or it will be a regular `SeqCst` fence.
+#[inline]
+pub fn barrier() {
+    imp::barrier()
+}
+
+#[cfg(all(target_os = "linux", feature = "membarrier", not(crossbeam_loom)))]
+mod imp {
+    use core::sync::atomic::AtomicUsize;
+    use core::sync::atomic::Ordering::Relaxed;
+
+    use libc;
+
+    const MEMBARRIER_CMD_QUERY: libc::c_int = 0;
+    const MEMBARRIER_CMD_PRIVATE_EXPEDITED: libc::c_int = 1 << 3;
+    const MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED: libc::c_int = 1 << 4;
+
+    const UNKNOWN: usize = 0;
+    const SUPPORTED: usize = 1;
+    const UNSUPPORTED: usize = 2;
+
+    /// Whether the system call is supported, or `UNKNOWN` if it hasn't been checked yet.
+    static STATE: AtomicUsize = AtomicUsize::new(UNKNOWN);
+
+    fn membarrier(cmd: libc::c_int) -> libc::c_long {
+        unsafe { libc::syscall(libc::SYS_membarrier, cmd, 0 as libc::c_int) }
+    }
+
+    #[cold]
+    fn detect() -> bool {
+        let mask = membarrier(MEMBARRIER_CMD_QUERY);
+        let supported = mask >= 0
+            && mask & MEMBARRIER_CMD_PRIVATE_EXPEDITED as libc::c_long != 0
+            && membarrier(MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED) == 0;
+
+        STATE.store(if supported { SUPPORTED } else { UNSUPPORTED }, Relaxed);
+        supported
+    }
+
+    #[inline]
+    pub fn is_supported() -> bool {
+        match STATE.load(Relaxed) {
+            UNKNOWN => detect(),
+            state => state == SUPPORTED,
+        }
+    }
+
+    #[inline]
+    pub fn barrier() {
+        if is_supported() {
+            // The process is registered, so the barrier can only fail if the kernel is broken.
+            // Continuing without it could free objects that are still in use.
+            assert_eq!(
+                membarrier(MEMBARRIER_CMD_PRIVATE_EXPEDITED),
+                0,
+                "membarrier failed"
+            );
+        } else {
+            super::atomic::fence(super::Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "membarrier", not(crossbeam_loom))))]
+mod imp {
+    use primitive::sync::atomic;
+
+    #[inline]
+    pub fn is_supported() -> bool {
+        false
+    }
+
+    #[inline]
+    pub fn barrier() {
+        atomic::fence(super::Ordering::SeqCst);
+    }
+}
+
+/// Executes the light side of an asymmetric fence.
+///
+/// This only prevents the compiler from reordering memory accesses around it.
+#[inline]
+pub fn light_barrier() {
+    atomic::compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{barrier, is_supported};
+
+    #[test]
+    fn barrier_works() {
+        // Either the system call is supported, or `barrier` falls back to a fence.
+        let supported = is_supported();
+        barrier();
+        assert_eq!(is_supported(), supported);
+    }
+}
@@ -0,0 +1,96 @@
+//! Synchronization primitives used by the collector and the lock-free data structures.
+//!
+//! When the crate is built with `RUSTFLAGS="--cfg crossbeam_loom"` and the `loom` feature, these
+//! are replaced with their [`loom`] counterparts so that the model checker can explore every
+//! interleaving and weak memory behavior of pinning, epoch advancement, garbage collection, and
+//! the `sync::queue` and `sync::list` algorithms.
+//!
+//! Only the primitives taking part in those algorithms are swapped. Statistics counters,
+//! bookkeeping such as the collector a data structure is bound to, and the hazard pointer domain
+//! keep using `core` atomics, which loom treats as opaque sequentially consistent memory.
+//!
+//! [`loom`]: https://docs.rs/loom
+
+#[cfg(crossbeam_loom)]
+pub mod cell {
+    pub use loom::cell::UnsafeCell;
+}
+
+#[cfg(not(crossbeam_loom))]
+pub mod cell {
+    /// An `UnsafeCell` with the closure-based interface of `loom::cell::UnsafeCell`.
+    #[derive(Debug)]
+    pub struct UnsafeCell<T>(::core::cell::UnsafeCell<T>);
+
+    impl<T> UnsafeCell<T> {
+        /// Creates a new cell containing `data`.
+        #[inline]
+        pub fn new(data: T) -> UnsafeCell<T> {
+            UnsafeCell(::core::cell::UnsafeCell::new(data))
+        }
+
+        /// Calls `f` with a shared pointer to the contents.
+        #[inline]
+        pub fn with<F, R>(&self, f: F) -> R
+        where
+            F: FnOnce(*const T) -> R,
+        {
+            f(self.0.get())
+        }
+
+        /// Calls `f` with a mutable pointer to the contents.
+        #[inline]
+        pub fn with_mut<F, R>(&self, f: F) -> R
+        where
+            F: FnOnce(*mut T) -> R,
+        {
+            f(self.0.get())
+        }
+    }
+}
+
+#[cfg(crossbeam_loom)]
+pub mod sync {
+    pub mod atomic {
+        use core::sync::atomic::Ordering;
+
+        pub use loom::sync::atomic::{fence, AtomicBool, AtomicUsize};
+
+        /// Loom doesn't model compiler fences, so a full fence is the closest approximation.
+        pub use self::fence as compiler_fence;
+
+        /// Loom doesn't model consume loads, so they are strengthened to acquire loads.
+        pub trait AtomicConsume {
+            /// Loads a value from the atomic using an acquire ordering.
+            fn load_consume(&self) -> usize;
+        }
+
+        impl AtomicConsume for AtomicUsize {
+            fn load_consume(&self) -> usize {
+                self.load(Ordering::Acquire)
+            }
+        }
+    }
+
+    pub use loom::sync::Arc;
+}
+
+#[cfg(not(crossbeam_loom))]
+pub mod sync {
+    pub mod atomic {
+        pub use core::sync::atomic::{compiler_fence, fence, AtomicBool, AtomicUsize};
+        pub use crossbeam_utils::AtomicConsume;
+    }
+
+    pub use alloc::sync::Arc;
+}
+
+#[cfg(crossbeam_loom)]
+pub mod thread {
+    pub use loom::thread::yield_now;
+}
+
+#[cfg(all(not(crossbeam_loom), feature = "use_std"))]
+pub mod thread {
+    pub use std::thread::yield_now;
+}
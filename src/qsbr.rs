@@ -0,0 +1,264 @@
+//! Quiescent-state-based reclamation.
+
+use core::fmt;
+
+use collector::{Collector, LocalHandle};
+use guard::Guard;
+
+/// A handle to a garbage collector that stays pinned between quiescent states.
+///
+/// This is a participant that stays pinned for its whole lifetime instead of pinning
+/// around every operation, so reading from data structures costs nothing beyond the atomic loads
+/// themselves. In return, the thread must periodically announce a *quiescent state*, a point at
+/// which it holds no references to shared objects, by calling [`quiescent_state`]. The global
+/// epoch can't advance past a participant that hasn't announced a quiescent state in a while.
+///
+/// Before blocking or idling for a long time, a thread should go [`offline`] so that it doesn't
+/// hold back garbage collection, and come back [`online`] afterwards.
+///
+/// QSBR participants can be registered with the same collector as regular [`LocalHandle`]s.
+///
+/// # Examples
+///
+/// ```
+/// use crossbeam_epoch::{Atomic, Collector, Owned};
+/// use std::sync::atomic::Ordering::{AcqRel, Acquire};
+///
+/// let collector = Collector::new();
+/// let mut handle = collector.register_qsbr();
+/// let a = Atomic::new(0);
+///
+/// for i in 1..100 {
+///     {
+///         let guard = handle.guard();
+///         let old = a.swap(Owned::new(i), AcqRel, guard);
+///         unsafe { guard.defer_destroy(old) };
+///     }
+///
+///     // No references loaded through the guard are held anymore.
+///     handle.quiescent_state();
+/// }
+///
+/// // Go offline while waiting for more work.
+/// handle.offline();
+/// # unsafe { drop(a.load(Acquire, crossbeam_epoch::unprotected()).into_owned()) };
+/// ```
+///
+/// [`quiescent_state`]: struct.QsbrHandle.html#method.quiescent_state
+/// [`offline`]: struct.QsbrHandle.html#method.offline
+/// [`online`]: struct.QsbrHandle.html#method.online
+/// [`LocalHandle`]: struct.LocalHandle.html
+pub struct QsbrHandle {
+    /// The guard keeping the participant pinned, or `None` if the participant is offline.
+    ///
+    /// This field must be dropped before `handle`.
+    guard: Option<Guard>,
+
+    handle: LocalHandle,
+}
+
+impl QsbrHandle {
+    /// Registers a new QSBR participant with `collector`. The participant starts online.
+    pub(crate) fn register(collector: &Collector) -> QsbrHandle {
+        let handle = collector.register();
+        QsbrHandle {
+            guard: Some(handle.pin()),
+            handle,
+        }
+    }
+
+    /// Returns the guard protecting loads while the participant is online.
+    ///
+    /// References obtained through the guard stay valid until the next call to
+    /// [`quiescent_state`] or [`offline`], which is enforced by their `&mut self` receiver.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the participant is offline.
+    ///
+    /// [`quiescent_state`]: struct.QsbrHandle.html#method.quiescent_state
+    /// [`offline`]: struct.QsbrHandle.html#method.offline
+    #[inline]
+    pub fn guard(&self) -> &Guard {
+        self.guard.as_ref().expect("QSBR participant is offline")
+    }
+
+    /// Announces that the thread holds no references to objects loaded through the guard.
+    ///
+    /// This lets the global epoch advance past the participant, and occasionally collects some
+    /// garbage. If the participant is offline, this method does nothing.
+    #[inline]
+    pub fn quiescent_state(&mut self) {
+        if let Some(ref guard) = self.guard {
+            unsafe { (*guard.local).quiesce(guard) }
+        }
+    }
+
+    /// Takes the participant offline, e.g. before a blocking call.
+    ///
+    /// An offline participant doesn't prevent the global epoch from advancing. If the participant
+    /// is already offline, this method does nothing.
+    pub fn offline(&mut self) {
+        self.guard = None;
+    }
+
+    /// Brings the participant back online after a call to [`offline`].
+    ///
+    /// If the participant is already online, this method does nothing.
+    ///
+    /// [`offline`]: struct.QsbrHandle.html#method.offline
+    pub fn online(&mut self) {
+        if self.guard.is_none() {
+            self.guard = Some(self.handle.pin());
+        }
+    }
+
+    /// Returns `true` if the participant is online.
+    #[inline]
+    pub fn is_online(&self) -> bool {
+        self.guard.is_some()
+    }
+
+    /// Returns the `Collector` associated with this handle.
+    #[inline]
+    pub fn collector(&self) -> &Collector {
+        self.handle.collector()
+    }
+}
+
+impl fmt::Debug for QsbrHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("QsbrHandle")
+            .field("online", &self.is_online())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    use std::sync::atomic::Ordering::{AcqRel, Relaxed};
+
+    use crossbeam_utils::thread;
+
+    use {unprotected, Atomic, Collector, Owned};
+
+    #[test]
+    fn quiescent_state() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        let collector = Collector::new();
+        let mut a = collector.register_qsbr();
+        let mut b = collector.register_qsbr();
+
+        a.guard().defer(|| DROPS.fetch_add(1, Relaxed));
+        a.guard().flush();
+
+        // `b` never announces a quiescent state, so the garbage can't be collected.
+        for _ in 0..100 {
+            a.quiescent_state();
+            a.guard().flush();
+        }
+        assert_eq!(DROPS.load(Relaxed), 0);
+
+        for _ in 0..100 {
+            a.quiescent_state();
+            b.quiescent_state();
+            a.guard().flush();
+        }
+        assert_eq!(DROPS.load(Relaxed), 1);
+    }
+
+    #[test]
+    fn offline() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        let collector = Collector::new();
+        let mut a = collector.register_qsbr();
+        let mut b = collector.register_qsbr();
+
+        b.offline();
+        assert!(!b.is_online());
+        b.quiescent_state();
+
+        a.guard().defer(|| DROPS.fetch_add(1, Relaxed));
+        for _ in 0..100 {
+            a.quiescent_state();
+            a.guard().flush();
+        }
+        assert_eq!(DROPS.load(Relaxed), 1);
+
+        b.online();
+        assert!(b.is_online());
+        a.guard().defer(|| DROPS.fetch_add(1, Relaxed));
+        for _ in 0..100 {
+            a.quiescent_state();
+            a.guard().flush();
+        }
+        assert_eq!(DROPS.load(Relaxed), 1);
+
+        drop(b);
+        for _ in 0..100 {
+            a.quiescent_state();
+            a.guard().flush();
+        }
+        assert_eq!(DROPS.load(Relaxed), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn guard_offline() {
+        let collector = Collector::new();
+        let mut handle = collector.register_qsbr();
+        handle.offline();
+        handle.guard();
+    }
+
+    #[test]
+    fn mixed_participants() {
+        const COUNT: usize = 10_000;
+        const THREADS: usize = 4;
+
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem;
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Relaxed);
+            }
+        }
+
+        let collector = Collector::new();
+        let a = Atomic::new(Elem);
+
+        thread::scope(|scope| {
+            for t in 0..THREADS {
+                let collector = &collector;
+                let a = &a;
+                scope.spawn(move || {
+                    let mut qsbr = collector.register_qsbr();
+                    let handle = collector.register();
+                    for i in 0..COUNT {
+                        if t % 2 == 0 {
+                            let guard = qsbr.guard();
+                            let old = a.swap(Owned::new(Elem), AcqRel, guard);
+                            unsafe { guard.defer_destroy(old) };
+                        } else {
+                            let guard = &handle.pin();
+                            let old = a.swap(Owned::new(Elem), AcqRel, guard);
+                            unsafe { guard.defer_destroy(old) };
+                        }
+                        if i % 16 == 0 {
+                            qsbr.quiescent_state();
+                        }
+                    }
+                });
+            }
+        });
+
+        drop(collector);
+        assert_eq!(DROPS.load(Relaxed), COUNT * THREADS);
+        unsafe { drop(a.load(Relaxed, unprotected()).into_owned()) };
+    }
+}
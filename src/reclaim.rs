@@ -0,0 +1,319 @@
+//! Traits abstracting over memory reclamation schemes.
+
+use core::sync::atomic::Ordering;
+
+use atomic::{Atomic, Pointable, Shared};
+use collector::{Collector, LocalHandle};
+use guard::Guard;
+use hazard::{self, HazardPointer};
+
+/// A memory reclamation scheme.
+pub trait Reclaim {
+    /// A participant registered with the scheme.
+    type Handle;
+
+    /// Registers a new participant.
+    fn register(&self) -> Self::Handle;
+}
+
+/// A participant that can defer destruction of unlinked objects.
+///
+/// A data structure that loads pointers through [`Protect`] and defers destruction through
+/// [`Retire`] can be used with both epoch-based reclamation and [`hazard`] pointers:
+///
+/// * [`Reclaim`] is implemented by a scheme, i.e. [`Collector`] and [`hazard::Domain`].
+/// * [`Retire`] is implemented by the participant performing an operation, i.e. a pinned
+///   [`Guard`] and a [`hazard::Handle`].
+/// * [`Protect`] is implemented by whatever protects a loaded pointer, i.e. a reference to a
+///   [`Guard`] and a [`hazard::HazardPointer`].
+///
+/// Epoch-based reclamation protects all loaded pointers at once, so its protectors are free. Every
+/// hazard pointer protects a single pointer, so an operation should only acquire as many
+/// protectors as it needs to dereference pointers at the same time.
+///
+/// The lifetime `'r` is the lifetime of the borrow protectors are acquired through. Code generic
+/// over the scheme usually requires `R: for<'r> Retire<'r>`.
+///
+/// # Examples
+///
+/// ```
+/// use crossbeam_epoch::{self as epoch, Atomic, Owned, Protect, Retire};
+/// use crossbeam_epoch::hazard::Domain;
+/// use std::sync::atomic::Ordering::{AcqRel, Acquire};
+///
+/// /// Replaces the value in `a`, returning a copy of the previous one.
+/// fn replace<R>(a: &Atomic<u64>, value: u64, r: &R) -> u64
+/// where
+///     R: for<'r> Retire<'r>,
+/// {
+///     let mut p = r.protector();
+///     let old = p.protect(a, Acquire);
+///     let result = unsafe { *old.deref() };
+///
+///     let old = a.swap(Owned::new(value), AcqRel, unsafe { epoch::unprotected() });
+///     unsafe { r.retire(old) };
+///     result
+/// }
+///
+/// let a = Atomic::new(1);
+///
+/// // With epoch-based reclamation.
+/// assert_eq!(replace(&a, 2, &epoch::pin()), 1);
+///
+/// // With hazard pointers.
+/// let handle = Domain::new().register();
+/// assert_eq!(replace(&a, 3, &handle), 2);
+/// # unsafe { drop(a.load(Acquire, epoch::unprotected()).into_owned()) };
+/// ```
+///
+/// [`Protect`]: trait.Protect.html
+/// [`Retire`]: trait.Retire.html
+/// [`Reclaim`]: trait.Reclaim.html
+/// [`hazard`]: hazard/index.html
+/// [`Collector`]: struct.Collector.html
+/// [`Guard`]: struct.Guard.html
+/// [`hazard::Domain`]: hazard/struct.Domain.html
+/// [`hazard::Handle`]: hazard/struct.Handle.html
+/// [`hazard::HazardPointer`]: hazard/struct.HazardPointer.html
+pub trait Retire<'r> {
+    /// The type protecting loaded pointers from being reclaimed.
+    type Protector: Protect;
+
+    /// Returns a new protector.
+    fn protector(&'r self) -> Self::Protector;
+
+    /// Stores a destructor for an object so that it can be deallocated and dropped once no thread
+    /// can be accessing it anymore.
+    ///
+    /// # Safety
+    ///
+    /// The object must be unreachable by other threads, i.e. no protector may start protecting it
+    /// after this call.
+    unsafe fn retire<T: ?Sized + Pointable>(&self, ptr: Shared<T>);
+
+    /// Stores a function so that it can be executed once no thread can be accessing the object
+    /// `ptr` points to anymore.
+    ///
+    /// # Safety
+    ///
+    /// The object must be unreachable by other threads, and it must be safe for another thread to
+    /// execute `f`.
+    unsafe fn retire_with<T, F, R>(&self, ptr: Shared<T>, f: F)
+    where
+        T: ?Sized + Pointable,
+        F: FnOnce() -> R;
+}
+
+/// A protector of pointers loaded from `Atomic`s.
+pub trait Protect {
+    /// Loads a pointer from `atomic` and protects the object it points to.
+    ///
+    /// The returned pointer may be dereferenced until the protector is used again.
+    fn protect<'a, T>(&'a mut self, atomic: &Atomic<T>, ord: Ordering) -> Shared<'a, T>
+    where
+        T: ?Sized + Pointable;
+
+    /// Stops protecting the last loaded object, if the scheme allows it.
+    fn reset(&mut self);
+}
+
+impl Reclaim for Collector {
+    type Handle = LocalHandle;
+
+    fn register(&self) -> LocalHandle {
+        Collector::register(self)
+    }
+}
+
+impl<'r> Retire<'r> for Guard {
+    type Protector = &'r Guard;
+
+    fn protector(&'r self) -> &'r Guard {
+        self
+    }
+
+    unsafe fn retire<T: ?Sized + Pointable>(&self, ptr: Shared<T>) {
+        self.defer_destroy(ptr);
+    }
+
+    unsafe fn retire_with<T, F, R>(&self, _: Shared<T>, f: F)
+    where
+        T: ?Sized + Pointable,
+        F: FnOnce() -> R,
+    {
+        self.defer_unchecked(f);
+    }
+}
+
+impl Protect for &Guard {
+    fn protect<'a, T>(&'a mut self, atomic: &Atomic<T>, ord: Ordering) -> Shared<'a, T>
+    where
+        T: ?Sized + Pointable,
+    {
+        atomic.load(ord, self)
+    }
+
+    /// Does nothing, since a guard protects all objects until it is dropped.
+    fn reset(&mut self) {}
+}
+
+impl Reclaim for hazard::Domain {
+    type Handle = hazard::Handle;
+
+    fn register(&self) -> hazard::Handle {
+        hazard::Domain::register(self)
+    }
+}
+
+impl<'r> Retire<'r> for hazard::Handle {
+    type Protector = HazardPointer<'r>;
+
+    fn protector(&'r self) -> HazardPointer<'r> {
+        self.hazard_pointer()
+    }
+
+    unsafe fn retire<T: ?Sized + Pointable>(&self, ptr: Shared<T>) {
+        self.defer_destroy(ptr);
+    }
+
+    unsafe fn retire_with<T, F, R>(&self, ptr: Shared<T>, f: F)
+    where
+        T: ?Sized + Pointable,
+        F: FnOnce() -> R,
+    {
+        self.defer_unchecked(ptr, f);
+    }
+}
+
+impl<'h> Protect for HazardPointer<'h> {
+    fn protect<'a, T>(&'a mut self, atomic: &Atomic<T>, ord: Ordering) -> Shared<'a, T>
+    where
+        T: ?Sized + Pointable,
+    {
+        HazardPointer::protect(self, atomic, ord)
+    }
+
+    fn reset(&mut self) {
+        HazardPointer::reset(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+    use crossbeam_utils::thread;
+
+    use {unprotected, Atomic, Collector, Owned};
+    use hazard::Domain;
+    use super::{Protect, Reclaim, Retire};
+
+    /// A Treiber stack written once for all reclamation schemes.
+    struct Stack {
+        head: Atomic<Node>,
+    }
+
+    struct Node {
+        value: usize,
+        next: Atomic<Node>,
+    }
+
+    static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Relaxed);
+        }
+    }
+
+    impl Stack {
+        fn push(&self, value: usize) {
+            let mut node = Owned::new(Node {
+                value,
+                next: Atomic::null(),
+            });
+            let guard = unsafe { unprotected() };
+            let mut head = self.head.load(Relaxed, guard);
+            loop {
+                node.next.store(head, Relaxed);
+                match self.head.compare_and_set_weak(head, node, Release, guard) {
+                    Ok(_) => return,
+                    Err(err) => {
+                        head = err.current;
+                        node = err.new;
+                    }
+                }
+            }
+        }
+
+        fn pop<R>(&self, r: &R) -> Option<usize>
+        where
+            R: for<'r> Retire<'r>,
+        {
+            let mut protector = r.protector();
+            loop {
+                let head = protector.protect(&self.head, Acquire);
+                let h = unsafe { head.as_ref()? };
+                let next = h.next.load(Relaxed, unsafe { unprotected() });
+                let value = h.value;
+
+                let guard = unsafe { unprotected() };
+                if self.head.compare_and_set(head, next, Release, guard).is_ok() {
+                    unsafe { r.retire(head) };
+                    protector.reset();
+                    return Some(value);
+                }
+            }
+        }
+    }
+
+    impl Drop for Stack {
+        fn drop(&mut self) {
+            let guard = unsafe { unprotected() };
+            let mut curr = self.head.load(Relaxed, guard);
+            while let Some(node) = unsafe { curr.as_ref() } {
+                let next = node.next.load(Relaxed, guard);
+                unsafe { drop(curr.into_owned()) };
+                curr = next;
+            }
+        }
+    }
+
+    /// Pushes and pops values concurrently using handles registered with `reclaim`.
+    fn stress<S, F>(reclaim: &S, pop: F)
+    where
+        S: Reclaim + Sync,
+        F: Fn(&Stack, &S::Handle) -> Option<usize> + Sync,
+    {
+        const COUNT: usize = 10_000;
+        const THREADS: usize = 4;
+
+        let stack = Stack {
+            head: Atomic::null(),
+        };
+        let popped = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| {
+                    let handle = reclaim.register();
+                    for i in 0..COUNT {
+                        stack.push(i);
+                        if pop(&stack, &handle).is_some() {
+                            popped.fetch_add(1, Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(popped.load(Relaxed), COUNT * THREADS);
+    }
+
+    #[test]
+    fn generic_stack() {
+        stress(&Collector::new(), |stack, handle| stack.pop(&handle.pin()));
+        stress(&Domain::new(), |stack, handle| stack.pop(handle));
+        assert_eq!(DROPS.load(Relaxed), 2 * 10_000 * 4);
+    }
+}
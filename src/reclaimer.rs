@@ -0,0 +1,154 @@
+//! Background garbage collection.
+//!
+//! By default, garbage is collected incrementally by the threads that pin and flush, which puts
+//! the cost of running destructors on them. A reclaimer is a dedicated thread that periodically
+//! advances the global epoch and executes all expired deferred functions instead. Combined with
+//! `CollectorBuilder::collect_on_pin(false)`, deferred functions run on the reclaimer thread only.
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use collector::Collector;
+
+/// Maximum number of collections per wakeup.
+///
+/// Two epoch advancements are enough for every queued bag to expire.
+const COLLECT_ATTEMPTS: usize = 3;
+
+/// A handle to a background thread collecting garbage on behalf of a collector.
+///
+/// The thread is stopped and joined when the handle is dropped.
+///
+/// Reclaimers are created by [`Collector::spawn_reclaimer`].
+///
+/// [`Collector::spawn_reclaimer`]: struct.Collector.html#method.spawn_reclaimer
+pub struct Reclaimer {
+    /// Set when the thread should exit.
+    stop: Arc<AtomicBool>,
+
+    /// The reclaimer thread.
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Reclaimer {
+    /// Spawns a reclaimer thread that collects garbage every `interval`.
+    pub(crate) fn spawn(collector: &Collector, interval: Duration) -> Reclaimer {
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let collector = collector.clone();
+            let stop = stop.clone();
+
+            thread::Builder::new()
+                .name("crossbeam-epoch-reclaimer".into())
+                .spawn(move || run(&collector, &stop, interval))
+                .expect("failed to spawn reclaimer thread")
+        };
+
+        Reclaimer {
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Stops the reclaimer thread and waits for it to exit.
+    ///
+    /// This is equivalent to dropping the handle.
+    pub fn stop(self) {}
+}
+
+impl Drop for Reclaimer {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.stop.store(true, Ordering::SeqCst);
+            thread.thread().unpark();
+
+            // Propagate a panic from a destructor executed on the reclaimer thread.
+            if thread.join().is_err() && !thread::panicking() {
+                panic!("reclaimer thread panicked");
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Reclaimer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Reclaimer").finish()
+    }
+}
+
+/// The body of the reclaimer thread.
+fn run(collector: &Collector, stop: &AtomicBool, interval: Duration) {
+    let handle = collector.register();
+
+    while !stop.load(Ordering::SeqCst) {
+        handle.pin().flush();
+
+        // A bag expires once the global epoch has advanced twice since it was sealed. The
+        // reclaimer's own pin prevents the global epoch from advancing more than once, so it
+        // repins between attempts. An attempt may destroy nothing while the epoch advances, so
+        // the loop is bounded by the number of attempts rather than by progress.
+        //
+        // Collecting produces garbage itself, so the reclaimer's bag is not flushed in this loop.
+        // Otherwise every collection would queue a bag for the next one.
+        for _ in 0..COLLECT_ATTEMPTS {
+            if !collector.global.has_garbage() {
+                break;
+            }
+            collector.global.collect_bags(usize::max_value(), &handle.pin());
+        }
+
+        thread::park_timeout(interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    use std::sync::atomic::Ordering;
+    use std::thread;
+    use std::time::Duration;
+
+    use CollectorBuilder;
+
+    #[test]
+    fn collects_in_background() {
+        const COUNT: usize = 10_000;
+        static DESTROYS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        let collector = CollectorBuilder::new().collect_on_pin(false).build();
+        let reclaimer = collector.spawn_reclaimer(Duration::from_millis(1));
+        let handle = collector.register();
+
+        for _ in 0..COUNT {
+            let guard = &handle.pin();
+            guard.defer(move || {
+                let current = thread::current();
+                assert_eq!(current.name(), Some("crossbeam-epoch-reclaimer"));
+                DESTROYS.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        handle.pin().flush();
+
+        while DESTROYS.load(Ordering::Relaxed) < COUNT {
+            thread::sleep(Duration::from_millis(1));
+        }
+        reclaimer.stop();
+        assert_eq!(DESTROYS.load(Ordering::Relaxed), COUNT);
+    }
+
+    #[test]
+    fn stop_on_drop() {
+        let collector = CollectorBuilder::new().build();
+        let reclaimer = collector.spawn_reclaimer(Duration::from_secs(3600));
+        while collector.stats().participants() == 0 {
+            thread::yield_now();
+        }
+
+        drop(reclaimer);
+        assert_eq!(collector.stats().participants(), 0);
+    }
+}
@@ -0,0 +1,202 @@
+//! Detection of stalled participants.
+//!
+//! A participant pinned in an old epoch prevents the global epoch from advancing, and with it all
+//! garbage collection. Whenever such a participant is found while attempting to advance the
+//! epoch, the time of the first such observation is recorded, which tells for how long the
+//! participant has been blocking the collector.
+
+use std::sync::Mutex;
+use std::thread::{self, Thread, ThreadId};
+use std::time::{Duration, Instant};
+
+use epoch::Epoch;
+
+/// A function called once a participant has been stalled for too long.
+pub type StallCallback = fn(&StalledParticipant);
+
+/// The thread a participant belongs to, along with its current stall.
+///
+/// Participant slots are reused by later registrations while other threads may still be observing
+/// them, so all of the information is behind a lock.
+#[derive(Debug)]
+pub struct StallInfo {
+    state: Mutex<State>,
+}
+
+/// The contents of `StallInfo`.
+#[derive(Debug)]
+struct State {
+    /// The thread the participant belongs to.
+    owner: Owner,
+
+    /// The stall currently observed, if any.
+    stall: Option<Stall>,
+}
+
+impl State {
+    /// Returns the state of a participant that hasn't been pinned yet.
+    fn new() -> Self {
+        State {
+            owner: Owner::Pending,
+            stall: None,
+        }
+    }
+}
+
+/// The thread a participant belongs to.
+///
+/// Looking up the current thread is relatively expensive and may panic while the thread is
+/// exiting, so it is only done when the participant is first pinned.
+#[derive(Debug)]
+enum Owner {
+    /// The participant hasn't been pinned yet.
+    Pending,
+
+    /// The participant belongs to a thread that can't be looked up.
+    Unknown,
+
+    /// The participant belongs to the given thread.
+    Known(Thread),
+}
+
+/// A stall of a participant pinned in a particular epoch.
+#[derive(Debug)]
+struct Stall {
+    /// The epoch the participant is pinned in.
+    epoch: Epoch,
+
+    /// The time when the participant was first observed blocking the global epoch.
+    since: Instant,
+
+    /// Whether the stall callback was already invoked for this stall.
+    reported: bool,
+}
+
+impl StallInfo {
+    /// Creates the information of a participant that hasn't been pinned yet.
+    pub fn new() -> Self {
+        StallInfo {
+            state: Mutex::new(State::new()),
+        }
+    }
+
+    /// Forgets the previous participant, since a new one is reusing the slot.
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = State::new();
+    }
+
+    /// Records the current thread as the owner of the participant, unless it is already known.
+    #[cold]
+    pub fn record_thread(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let Owner::Pending = state.owner {
+            state.owner = Owner::Known(thread::current());
+        }
+    }
+
+    /// Prevents the current thread from being looked up, e.g. because it is exiting.
+    pub fn forget_thread(&self) {
+        self.state.lock().unwrap().owner = Owner::Unknown;
+    }
+
+    /// Calls `f` with the ID of the thread the participant belongs to, if known.
+    ///
+    /// The participant can't be taken over by another thread until `f` returns.
+    pub fn with_thread_id<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(Option<ThreadId>) -> R,
+    {
+        let state = self.state.lock().unwrap();
+        f(match state.owner {
+            Owner::Known(ref thread) => Some(thread.id()),
+            Owner::Pending | Owner::Unknown => None,
+        })
+    }
+
+    /// Records that the participant pinned in `epoch` is blocking the global epoch.
+    ///
+    /// Returns for how long the participant has been blocking, and whether this is the first
+    /// observation of the stall lasting at least `report_after`.
+    pub fn observe(&self, epoch: Epoch, report_after: Option<Duration>) -> (Duration, bool) {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let stall = &mut state.stall;
+
+        match *stall {
+            Some(ref mut stall) if stall.epoch == epoch => {
+                let blocking_for = now.duration_since(stall.since);
+                let report = match report_after {
+                    Some(threshold) => !stall.reported && blocking_for >= threshold,
+                    None => false,
+                };
+                if report {
+                    stall.reported = true;
+                }
+                return (blocking_for, report);
+            }
+            _ => {}
+        }
+
+        *stall = Some(Stall {
+            epoch,
+            since: now,
+            reported: false,
+        });
+        (Duration::from_secs(0), false)
+    }
+
+    /// Returns a report about the participant.
+    pub fn participant(&self, epoch_lag: usize, blocking_for: Duration) -> StalledParticipant {
+        let state = self.state.lock().unwrap();
+        StalledParticipant {
+            thread: match state.owner {
+                Owner::Known(ref thread) => Some(thread.clone()),
+                Owner::Pending | Owner::Unknown => None,
+            },
+            epoch_lag,
+            blocking_for,
+        }
+    }
+}
+
+/// A participant that prevents the global epoch from advancing.
+///
+/// Stalled participants are obtained by calling [`Collector::stalled_participants`] or passed to
+/// the callback set with [`CollectorBuilder::stall_callback`].
+///
+/// [`Collector::stalled_participants`]: struct.Collector.html#method.stalled_participants
+/// [`CollectorBuilder::stall_callback`]: struct.CollectorBuilder.html#method.stall_callback
+#[derive(Clone, Debug)]
+pub struct StalledParticipant {
+    thread: Option<Thread>,
+    epoch_lag: usize,
+    blocking_for: Duration,
+}
+
+impl StalledParticipant {
+    /// Returns the ID of the thread the participant belongs to.
+    ///
+    /// Returns `None` if the participant was used by the default collector while its thread was
+    /// exiting, in which case the thread can't be identified.
+    pub fn thread_id(&self) -> Option<ThreadId> {
+        self.thread.as_ref().map(|thread| thread.id())
+    }
+
+    /// Returns the name of the thread the participant belongs to, if it is known and has one.
+    pub fn thread_name(&self) -> Option<&str> {
+        self.thread.as_ref().and_then(|thread| thread.name())
+    }
+
+    /// Returns the number of epochs the participant lags behind the global epoch.
+    pub fn epoch_lag(&self) -> usize {
+        self.epoch_lag
+    }
+
+    /// Returns for how long the participant has been observed blocking the global epoch.
+    ///
+    /// Stalls are only observed when some thread attempts to advance the global epoch, so this
+    /// is a lower bound on the time the participant has been pinned.
+    pub fn blocking_for(&self) -> Duration {
+        self.blocking_for
+    }
+}
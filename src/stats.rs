@@ -0,0 +1,119 @@
+//! Statistics about garbage collection.
+//!
+//! Every collector maintains a set of counters that are updated with relaxed atomic operations on
+//! the slow paths of garbage collection (sealing bags, advancing the epoch, registering
+//! participants), so keeping them up to date adds no cost to pinning or deferring.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The counters maintained by a collector.
+#[derive(Debug, Default)]
+pub struct Counters {
+    /// Number of deferred functions in sealed bags residing in the global queue.
+    pub queued_deferreds: AtomicUsize,
+
+    /// Number of bytes accounted to deferred functions in sealed bags residing in the global
+    /// queue.
+    pub queued_bytes: AtomicUsize,
+
+    /// Number of bags sealed and pushed into the global queue.
+    pub sealed_bags: AtomicUsize,
+
+    /// Number of sealed bags popped from the global queue and destroyed.
+    pub collected_bags: AtomicUsize,
+
+    /// Number of successful global epoch advancements.
+    pub epoch_advances: AtomicUsize,
+
+    /// Number of attempts to advance the global epoch that failed because a participant was
+    /// pinned in an older epoch.
+    pub blocked_advances: AtomicUsize,
+
+    /// Number of participants currently registered.
+    pub participants: AtomicUsize,
+}
+
+impl Counters {
+    /// Takes a snapshot of the counters.
+    pub fn snapshot(&self) -> CollectorStats {
+        CollectorStats {
+            queued_deferreds: self.queued_deferreds.load(Ordering::Relaxed),
+            queued_bytes: self.queued_bytes.load(Ordering::Relaxed),
+            sealed_bags: self.sealed_bags.load(Ordering::Relaxed),
+            collected_bags: self.collected_bags.load(Ordering::Relaxed),
+            epoch_advances: self.epoch_advances.load(Ordering::Relaxed),
+            blocked_advances: self.blocked_advances.load(Ordering::Relaxed),
+            participants: self.participants.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A snapshot of garbage collection statistics.
+///
+/// Statistics are obtained by calling [`Collector::stats`]. Each counter is read independently
+/// with a relaxed load, so a snapshot taken while other threads are using the collector is not
+/// guaranteed to be consistent across counters.
+///
+/// [`Collector::stats`]: struct.Collector.html#method.stats
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CollectorStats {
+    queued_deferreds: usize,
+    queued_bytes: usize,
+    sealed_bags: usize,
+    collected_bags: usize,
+    epoch_advances: usize,
+    blocked_advances: usize,
+    participants: usize,
+}
+
+impl CollectorStats {
+    /// Returns the number of deferred functions waiting in the global queue.
+    ///
+    /// Deferred functions still residing in thread-local bags are not included.
+    pub fn queued_deferreds(&self) -> usize {
+        self.queued_deferreds
+    }
+
+    /// Returns the number of bytes accounted to deferred functions waiting in the global queue.
+    ///
+    /// See [`GarbageLimit::Bytes`] for how the size of a deferred function is determined.
+    ///
+    /// [`GarbageLimit::Bytes`]: enum.GarbageLimit.html#variant.Bytes
+    pub fn queued_bytes(&self) -> usize {
+        self.queued_bytes
+    }
+
+    /// Returns the number of sealed bags waiting in the global queue.
+    pub fn queued_bags(&self) -> usize {
+        self.sealed_bags.wrapping_sub(self.collected_bags)
+    }
+
+    /// Returns the total number of bags that were sealed and pushed into the global queue.
+    pub fn sealed_bags(&self) -> usize {
+        self.sealed_bags
+    }
+
+    /// Returns the total number of sealed bags that were destroyed.
+    pub fn collected_bags(&self) -> usize {
+        self.collected_bags
+    }
+
+    /// Returns the total number of successful global epoch advancements.
+    pub fn epoch_advances(&self) -> usize {
+        self.epoch_advances
+    }
+
+    /// Returns the total number of attempts to advance the global epoch that failed because a
+    /// participant was pinned in an older epoch.
+    ///
+    /// A steadily growing number of blocked advancements usually means that some thread holds
+    /// on to a guard for too long.
+    pub fn blocked_advances(&self) -> usize {
+        self.blocked_advances
+    }
+
+    /// Returns the number of currently registered participants.
+    pub fn participants(&self) -> usize {
+        self.participants
+    }
+}
@@ -0,0 +1,377 @@
+//! An atomic pointer to a reference-counted object.
+//!
+//! Readers load the pointer under a guard without touching the reference count. If the object
+//! must outlive the guard, the loaded reference can be upgraded to an [`Arc`] by incrementing the
+//! strong count, which is safe because the reference held by the pointer itself is only released
+//! once all threads that might have loaded it are unpinned.
+//!
+//! [`Arc`]: https://doc.rust-lang.org/std/sync/struct.Arc.html
+
+use core::fmt;
+use core::mem::ManuallyDrop;
+use core::ops::Deref;
+use core::ptr;
+use core::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed};
+use alloc::sync::Arc;
+
+use {unprotected, Atomic, Collector, Guard, Shared};
+use super::check_guard;
+
+/// An atomic pointer to an object managed by an [`Arc`].
+///
+/// The pointer holds one strong reference to the object it points to. When the object is
+/// replaced, releasing that reference is deferred until all threads currently pinned have been
+/// unpinned, so references loaded under a guard stay valid for as long as the guard is alive.
+///
+/// The pointer is tied to a [`Collector`], and every guard passed to its methods must belong to
+/// that collector. Pointers created with [`new`] use the default collector, so guards obtained
+/// from [`pin`] can be used with them.
+///
+/// # Panics
+///
+/// Methods taking a guard panic if the guard belongs to a different collector.
+///
+/// # Examples
+///
+/// ```
+/// use crossbeam_epoch::{self as epoch, sync::AtomicArc};
+/// use std::sync::Arc;
+///
+/// let a = AtomicArc::new(Arc::new(1));
+///
+/// let arc = {
+///     let guard = &epoch::pin();
+///     let r = a.load(guard);
+///     assert_eq!(*r, 1);
+///
+///     // Keep the object alive after unpinning.
+///     r.to_arc()
+/// };
+///
+/// a.store(Arc::new(2), &epoch::pin());
+/// assert_eq!(*arc, 1);
+/// ```
+///
+/// [`Arc`]: https://doc.rust-lang.org/std/sync/struct.Arc.html
+/// [`Collector`]: ../../struct.Collector.html
+/// [`new`]: struct.AtomicArc.html#method.new
+/// [`pin`]: ../../fn.pin.html
+pub struct AtomicArc<T> {
+    /// The current object, obtained with `Arc::into_raw`.
+    ///
+    /// This pointer is never null, except in `into_arc` right before the pointer is dropped.
+    inner: Atomic<T>,
+    collector: Collector,
+}
+
+impl<T> AtomicArc<T> {
+    /// Creates a new atomic pointer to `arc` using the default collector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::sync::AtomicArc;
+    /// use std::sync::Arc;
+    ///
+    /// let a = AtomicArc::new(Arc::new(1234));
+    /// ```
+    #[cfg(feature = "use_std")]
+    pub fn new(arc: Arc<T>) -> AtomicArc<T> {
+        Self::with_collector(arc, ::default_collector().clone())
+    }
+
+    /// Creates a new atomic pointer to `arc` using the given collector.
+    pub fn with_collector(arc: Arc<T>, collector: Collector) -> AtomicArc<T> {
+        let inner = Atomic::null();
+        inner.store(Shared::from(Arc::into_raw(arc)), Relaxed);
+        AtomicArc { inner, collector }
+    }
+
+    /// Returns the collector the pointer is tied to.
+    pub fn collector(&self) -> &Collector {
+        &self.collector
+    }
+
+    /// Returns a reference to the current object.
+    ///
+    /// The reference stays valid until the guard is dropped, and can be upgraded to an [`Arc`]
+    /// with [`ArcRef::to_arc`].
+    ///
+    /// [`Arc`]: https://doc.rust-lang.org/std/sync/struct.Arc.html
+    /// [`ArcRef::to_arc`]: struct.ArcRef.html#method.to_arc
+    pub fn load<'g>(&self, guard: &'g Guard) -> ArcRef<'g, T> {
+        check_guard(&self.collector, guard);
+        ArcRef {
+            value: unsafe { self.inner.load(Acquire, guard).deref() },
+        }
+    }
+
+    /// Consumes the atomic pointer and returns the current object.
+    pub fn into_arc(self) -> Arc<T> {
+        unsafe {
+            let ptr = self.inner.swap(Shared::null(), Relaxed, unprotected());
+            Arc::from_raw(ptr.as_raw())
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> AtomicArc<T> {
+    /// Replaces the current object with `arc`.
+    ///
+    /// Releasing the reference to the previous object is deferred until all threads currently
+    /// pinned have been unpinned.
+    pub fn store(&self, arc: Arc<T>, guard: &Guard) {
+        check_guard(&self.collector, guard);
+
+        let old = self.inner.swap(Shared::from(Arc::into_raw(arc)), AcqRel, guard);
+        unsafe { Self::release(old, guard) }
+    }
+
+    /// Replaces the current object with `arc` and returns the previous one.
+    ///
+    /// Releasing the reference held by the pointer is deferred as with [`store`], so the returned
+    /// [`Arc`] is never the last reference while other threads may still be reading the object.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, sync::AtomicArc};
+    /// use std::sync::Arc;
+    ///
+    /// let a = AtomicArc::new(Arc::new(1));
+    /// let guard = &epoch::pin();
+    /// assert_eq!(*a.swap(Arc::new(2), guard), 1);
+    /// assert_eq!(*a.load(guard), 2);
+    /// ```
+    ///
+    /// [`store`]: struct.AtomicArc.html#method.store
+    /// [`Arc`]: https://doc.rust-lang.org/std/sync/struct.Arc.html
+    pub fn swap(&self, arc: Arc<T>, guard: &Guard) -> Arc<T> {
+        check_guard(&self.collector, guard);
+
+        let old = self.inner.swap(Shared::from(Arc::into_raw(arc)), AcqRel, guard);
+        unsafe {
+            let old_arc = ArcRef { value: old.deref() }.to_arc();
+            Self::release(old, guard);
+            old_arc
+        }
+    }
+
+    /// Stores `new` if the current object is `current`, comparing by address.
+    ///
+    /// On success, returns a reference to the previous object. On failure, returns the actual
+    /// current object and gives `new` back.
+    ///
+    /// Releasing the reference to the previous object is deferred as with [`store`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, sync::AtomicArc};
+    /// use std::sync::Arc;
+    ///
+    /// let a = AtomicArc::new(Arc::new(1));
+    /// let guard = &epoch::pin();
+    ///
+    /// let current = a.load(guard);
+    /// assert!(a.compare_and_set(current, Arc::new(2), guard).is_ok());
+    /// assert!(a.compare_and_set(current, Arc::new(3), guard).is_err());
+    /// assert_eq!(*a.load(guard), 2);
+    /// ```
+    ///
+    /// [`store`]: struct.AtomicArc.html#method.store
+    pub fn compare_and_set<'g>(
+        &self,
+        current: ArcRef<'g, T>,
+        new: Arc<T>,
+        guard: &'g Guard,
+    ) -> Result<ArcRef<'g, T>, (ArcRef<'g, T>, Arc<T>)> {
+        check_guard(&self.collector, guard);
+
+        let current = Shared::from(current.value as *const T);
+        let new = Shared::from(Arc::into_raw(new));
+        match self.inner.compare_and_set(current, new, AcqRel, guard) {
+            Ok(_) => unsafe {
+                Self::release(current, guard);
+                Ok(ArcRef { value: current.deref() })
+            },
+            Err(err) => unsafe {
+                let actual = ArcRef { value: err.current.deref() };
+                Err((actual, Arc::from_raw(new.as_raw())))
+            },
+        }
+    }
+
+    /// Defers releasing the reference held by the pointer to `old`, which was just unlinked.
+    unsafe fn release(old: Shared<T>, guard: &Guard) {
+        let arc = Arc::from_raw(old.as_raw());
+        guard.defer(move || drop(arc));
+    }
+}
+
+impl<T> Drop for AtomicArc<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let ptr = self.inner.load(Relaxed, unprotected());
+            if !ptr.is_null() {
+                drop(Arc::from_raw(ptr.as_raw()));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "use_std")]
+impl<T: Default> Default for AtomicArc<T> {
+    fn default() -> Self {
+        AtomicArc::new(Arc::new(T::default()))
+    }
+}
+
+impl<T> fmt::Debug for AtomicArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AtomicArc").finish()
+    }
+}
+
+/// A reference to an object loaded from an [`AtomicArc`], valid while the guard is alive.
+///
+/// This struct is created by [`AtomicArc::load`].
+///
+/// [`AtomicArc`]: struct.AtomicArc.html
+/// [`AtomicArc::load`]: struct.AtomicArc.html#method.load
+pub struct ArcRef<'g, T: 'g> {
+    /// The object, which is always managed by an `Arc`.
+    value: &'g T,
+}
+
+impl<'g, T> ArcRef<'g, T> {
+    /// Increments the strong count of the object and returns a new [`Arc`] to it.
+    ///
+    /// The returned [`Arc`] keeps the object alive after the guard is dropped.
+    ///
+    /// [`Arc`]: https://doc.rust-lang.org/std/sync/struct.Arc.html
+    pub fn to_arc(&self) -> Arc<T> {
+        // The atomic pointer holds a strong reference until all threads that might have loaded
+        // the object are unpinned, so the count is positive.
+        let arc = ManuallyDrop::new(unsafe { Arc::from_raw(self.value as *const T) });
+        Arc::clone(&arc)
+    }
+
+    /// Returns `true` if both references point to the same object.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        ptr::eq(this.value, other.value)
+    }
+}
+
+impl<'g, T> Clone for ArcRef<'g, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'g, T> Copy for ArcRef<'g, T> {}
+
+impl<'g, T> Deref for ArcRef<'g, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'g, T: fmt::Debug> fmt::Debug for ArcRef<'g, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.value, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    use std::sync::atomic::Ordering;
+
+    use crossbeam_utils::thread;
+
+    use {pin, Collector};
+    use super::{ArcRef, AtomicArc};
+
+    #[test]
+    fn load_store() {
+        let a = AtomicArc::new(Arc::new(1));
+        let guard = &pin();
+
+        let one = a.load(guard);
+        a.store(Arc::new(2), guard);
+        assert_eq!(*one, 1);
+        assert_eq!(*a.load(guard), 2);
+
+        let two = a.swap(Arc::new(3), guard);
+        assert_eq!(*two, 2);
+        assert_eq!(*a.load(guard), 3);
+
+        let three = a.load(guard);
+        assert!(ArcRef::ptr_eq(&three, &a.load(guard)));
+        assert!(a.compare_and_set(one, Arc::new(4), guard).is_err());
+        assert!(a.compare_and_set(three, Arc::new(4), guard).is_ok());
+        assert_eq!(*a.into_arc(), 4);
+    }
+
+    #[test]
+    fn deferred_release() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem;
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let collector = Collector::new();
+        let handle = collector.register();
+        let a = AtomicArc::with_collector(Arc::new(Elem), collector.clone());
+
+        let arc = a.load(&handle.pin()).to_arc();
+        a.store(Arc::new(Elem), &handle.pin());
+        let swapped = a.swap(Arc::new(Elem), &handle.pin());
+
+        // The pointer's references are only released when the garbage is collected.
+        assert_eq!(Arc::strong_count(&swapped), 2);
+        drop(a);
+        drop(handle);
+        drop(collector);
+        assert_eq!(Arc::strong_count(&swapped), 1);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+        drop(arc);
+        drop(swapped);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn concurrent_upgrade() {
+        const COUNT: usize = 10_000;
+        const THREADS: usize = 4;
+
+        let a = AtomicArc::new(Arc::new(0));
+
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| for i in 0..COUNT {
+                    let arc = a.load(&pin()).to_arc();
+                    a.store(Arc::new(i), &pin());
+                    assert!(*arc < COUNT);
+                });
+            }
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_collector() {
+        let a = AtomicArc::with_collector(Arc::new(1), Collector::new());
+        a.load(&pin());
+    }
+}
@@ -0,0 +1,308 @@
+//! A read-mostly cell protected by epoch-based garbage collection.
+//!
+//! Readers load a reference to the current value under a guard without any locking or reference
+//! counting. Writers replace the value and defer destruction of the previous one until all readers
+//! that might still hold a reference to it have unpinned.
+
+use core::fmt;
+use core::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed};
+
+use {unprotected, Atomic, Collector, Guard, Owned, Shared};
+use super::check_guard;
+
+/// A cell holding a value that is read often and replaced rarely.
+///
+/// Loading the value is as cheap as an atomic load, and the returned reference stays valid for as
+/// long as the guard is alive. Replaced values are destroyed once no guard can observe them.
+///
+/// The cell is tied to a [`Collector`], and every guard passed to its methods must belong to that
+/// collector. Cells created with [`new`] use the default collector, so guards obtained from
+/// [`pin`] can be used with them.
+///
+/// # Panics
+///
+/// Methods taking a guard panic if the guard belongs to a different collector.
+///
+/// # Examples
+///
+/// ```
+/// use crossbeam_epoch::{self as epoch, sync::EpochCell};
+///
+/// let config = EpochCell::new(String::from("initial"));
+///
+/// let guard = &epoch::pin();
+/// let old = config.load(guard);
+/// config.store(String::from("updated"), guard);
+///
+/// // The old value is still valid while the guard is alive.
+/// assert_eq!(old, "initial");
+/// assert_eq!(config.load(guard), "updated");
+/// ```
+///
+/// [`Collector`]: ../../struct.Collector.html
+/// [`new`]: struct.EpochCell.html#method.new
+/// [`pin`]: ../../fn.pin.html
+pub struct EpochCell<T> {
+    /// The current value.
+    ///
+    /// This pointer is never null, except in `into_inner` right before the cell is dropped.
+    inner: Atomic<T>,
+    collector: Collector,
+}
+
+impl<T> EpochCell<T> {
+    /// Creates a new cell holding `value` using the default collector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::sync::EpochCell;
+    ///
+    /// let cell = EpochCell::new(1234);
+    /// ```
+    #[cfg(feature = "use_std")]
+    pub fn new(value: T) -> EpochCell<T> {
+        Self::with_collector(value, ::default_collector().clone())
+    }
+
+    /// Creates a new cell holding `value` using the given collector.
+    pub fn with_collector(value: T, collector: Collector) -> EpochCell<T> {
+        EpochCell {
+            inner: Atomic::new(value),
+            collector,
+        }
+    }
+
+    /// Returns the collector the cell is tied to.
+    pub fn collector(&self) -> &Collector {
+        &self.collector
+    }
+
+    /// Returns a reference to the current value.
+    ///
+    /// The value may be concurrently replaced by another thread, but the reference stays valid
+    /// until the guard is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, sync::EpochCell};
+    ///
+    /// let cell = EpochCell::new(1234);
+    /// assert_eq!(*cell.load(&epoch::pin()), 1234);
+    /// ```
+    pub fn load<'g>(&self, guard: &'g Guard) -> &'g T {
+        check_guard(&self.collector, guard);
+        unsafe { self.inner.load(Acquire, guard).deref() }
+    }
+
+    /// Returns a mutable reference to the current value.
+    ///
+    /// This call borrows the cell mutably, so no other thread can be accessing it.
+    pub fn get_mut(&mut self) -> &mut T {
+        let ptr = self.inner.load(Relaxed, unsafe { unprotected() }).as_raw();
+        unsafe { &mut *(ptr as *mut T) }
+    }
+
+    /// Consumes the cell and returns the current value.
+    pub fn into_inner(self) -> T {
+        unsafe {
+            let ptr = self.inner.swap(Shared::null(), Relaxed, unprotected());
+            *ptr.into_owned().into_box()
+        }
+    }
+}
+
+impl<T: Send + 'static> EpochCell<T> {
+    /// Replaces the current value with `value`.
+    ///
+    /// Destruction of the previous value is deferred until all threads currently pinned have been
+    /// unpinned.
+    pub fn store(&self, value: T, guard: &Guard) {
+        self.swap(value, guard);
+    }
+
+    /// Replaces the current value with `value` and returns a reference to the previous one.
+    ///
+    /// Destruction of the previous value is deferred until all threads currently pinned have been
+    /// unpinned, so the returned reference stays valid until the guard is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, sync::EpochCell};
+    ///
+    /// let cell = EpochCell::new(1);
+    /// let guard = &epoch::pin();
+    /// assert_eq!(*cell.swap(2, guard), 1);
+    /// assert_eq!(*cell.load(guard), 2);
+    /// ```
+    pub fn swap<'g>(&self, value: T, guard: &'g Guard) -> &'g T {
+        check_guard(&self.collector, guard);
+
+        let old = self.inner.swap(Owned::new(value), AcqRel, guard);
+        unsafe {
+            guard.defer_destroy(old);
+            old.deref()
+        }
+    }
+
+    /// Replaces the current value with one computed from it by `f`, and returns a reference to the
+    /// previous value.
+    ///
+    /// If another thread replaces the value between reading it and storing the result of `f`, `f`
+    /// is called again on the newer value. Hence `f` may be called multiple times, and should not
+    /// have side effects.
+    ///
+    /// Destruction of the previous value is deferred as with [`swap`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, sync::EpochCell};
+    ///
+    /// let cell = EpochCell::new(vec![1, 2]);
+    /// let guard = &epoch::pin();
+    ///
+    /// let old = cell.rcu(|v| {
+    ///     let mut v = v.clone();
+    ///     v.push(3);
+    ///     v
+    /// }, guard);
+    ///
+    /// assert_eq!(*old, [1, 2]);
+    /// assert_eq!(*cell.load(guard), [1, 2, 3]);
+    /// ```
+    ///
+    /// [`swap`]: struct.EpochCell.html#method.swap
+    pub fn rcu<'g, F>(&self, mut f: F, guard: &'g Guard) -> &'g T
+    where
+        F: FnMut(&T) -> T,
+    {
+        check_guard(&self.collector, guard);
+
+        let mut current = self.inner.load(Acquire, guard);
+        let mut new = Owned::new(f(unsafe { current.deref() }));
+        loop {
+            match self.inner.compare_and_set(current, new, AcqRel, guard) {
+                Ok(_) => unsafe {
+                    guard.defer_destroy(current);
+                    return current.deref();
+                },
+                Err(err) => {
+                    current = err.current;
+                    new = err.new;
+                    *new = f(unsafe { current.deref() });
+                }
+            }
+        }
+    }
+}
+
+impl<T> Drop for EpochCell<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let ptr = self.inner.load(Relaxed, unprotected());
+            if !ptr.is_null() {
+                drop(ptr.into_owned());
+            }
+        }
+    }
+}
+
+#[cfg(feature = "use_std")]
+impl<T: Default> Default for EpochCell<T> {
+    fn default() -> Self {
+        EpochCell::new(T::default())
+    }
+}
+
+impl<T> fmt::Debug for EpochCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EpochCell").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    use std::sync::atomic::Ordering;
+
+    use crossbeam_utils::thread;
+
+    use {pin, Collector};
+    use super::EpochCell;
+
+    #[test]
+    fn load_store() {
+        let mut cell = EpochCell::new(1);
+        {
+            let guard = &pin();
+            assert_eq!(*cell.load(guard), 1);
+            cell.store(2, guard);
+            assert_eq!(*cell.load(guard), 2);
+            assert_eq!(*cell.swap(3, guard), 2);
+            assert_eq!(*cell.load(guard), 3);
+        }
+        *cell.get_mut() += 1;
+        assert_eq!(cell.into_inner(), 4);
+    }
+
+    #[test]
+    fn drops() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem;
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let collector = Collector::new();
+        let handle = collector.register();
+
+        let cell = EpochCell::with_collector(Elem, collector.clone());
+        for _ in 0..10 {
+            cell.store(Elem, &handle.pin());
+        }
+        for _ in 0..10 {
+            cell.rcu(|_| Elem, &handle.pin());
+        }
+        drop(cell);
+        drop(handle);
+        drop(collector);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 21);
+
+        let cell = EpochCell::new(Elem);
+        drop(cell.into_inner());
+        assert_eq!(DROPS.load(Ordering::Relaxed), 22);
+    }
+
+    #[test]
+    fn concurrent_rcu() {
+        const COUNT: usize = 10_000;
+        const THREADS: usize = 4;
+
+        let cell = EpochCell::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| for _ in 0..COUNT {
+                    let guard = &pin();
+                    let old = *cell.rcu(|x| x + 1, guard);
+                    assert!(*cell.load(guard) > old);
+                });
+            }
+        });
+        assert_eq!(cell.into_inner(), COUNT * THREADS);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_collector() {
+        let cell = EpochCell::with_collector(1, Collector::new());
+        cell.load(&pin());
+    }
+}
@@ -1,17 +1,70 @@
 //! Lock-free intrusive linked list.
 //!
+//! Elements of the list embed an [`Entry`], and an implementation of [`IsElement`] converts
+//! between an element and its entry. The easiest way to implement it is the
+//! [`impl_is_element!`] macro.
+//!
+//! Elements are inserted at the head of the list and removed by marking their entry as deleted.
+//! Marked entries are unlinked by iterators passing over them, and the elements are then
+//! deallocated once no thread can be referencing them anymore.
+//!
+//! A list is bound to the collector of the first guard it is used with, and using it with a guard
+//! of another collector panics.
+//!
+//! # Examples
+//!
+//! ```
+//! #[macro_use]
+//! extern crate crossbeam_epoch as epoch;
+//!
+//! use epoch::Owned;
+//! use epoch::sync::list::{Entry, List};
+//!
+//! struct Subscriber {
+//!     entry: Entry,
+//!     id: usize,
+//! }
+//!
+//! impl_is_element!(Subscriber, entry);
+//!
+//! fn main() {
+//!     let list: List<Subscriber> = List::new();
+//!     let guard = &epoch::pin();
+//!
+//!     for id in 0..3 {
+//!         list.push(Owned::new(Subscriber { entry: Entry::default(), id }), guard);
+//!     }
+//!
+//!     let first = list.iter(guard).next().unwrap().unwrap();
+//!     assert_eq!(first.id, 2);
+//!     assert!(list.remove(first, guard));
+//!
+//!     let ids: Vec<usize> = list.iter(guard).filter_map(|s| s.ok()).map(|s| s.id).collect();
+//!     assert_eq!(ids, [1, 0]);
+//! }
+//! ```
+//!
 //! Ideas from Michael.  High Performance Dynamic Lock-Free Hash Tables and List-Based Sets.  SPAA
 //! 2002.  http://dl.acm.org/citation.cfm?id=564870.564881
+//!
+//! [`Entry`]: struct.Entry.html
+//! [`IsElement`]: trait.IsElement.html
+//! [`impl_is_element!`]: ../../macro.impl_is_element.html
 
+use core::fmt;
 use core::marker::PhantomData;
+use core::sync::atomic::AtomicUsize;
 use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
 
-use {Atomic, Shared, Guard, unprotected};
+use {Atomic, Owned, Shared, Guard, unprotected};
+use super::bind_guard;
 
 /// An entry in a linked list.
 ///
 /// An Entry is accessed from multiple threads, so it would be beneficial to put it in a different
 /// cache-line than thread-local data in terms of performance.
+///
+/// A new entry is created with `Entry::default()`.
 #[derive(Debug)]
 pub struct Entry {
     /// The next entry in the linked list.
@@ -23,22 +76,42 @@ pub struct Entry {
 /// linked list defined in this module. `T` has to contain (or otherwise be linked to) an instance
 /// of `Entry`.
 ///
+/// Implementations are usually generated with the [`impl_is_element!`] macro.
+///
+/// # Safety
+///
+/// Implementors must guarantee that:
+///
+/// - `entry_of` returns an entry that is owned by the given element and used by no other
+///   implementation of `IsElement` for `T`,
+/// - `element_of` is the inverse of `entry_of`,
+/// - `finalize` deallocates the element as if it was an `Owned<T>` (or a `Box<T>`) that is being
+///   dropped.
+///
 /// # Example
 ///
-/// ```ignore
+/// The offset of the entry is computed with the `offset_of!` macro from the `memoffset` crate,
+/// which doesn't create references to uninitialized memory.
+///
+/// ```
+/// extern crate crossbeam_epoch as epoch;
+/// #[macro_use]
+/// extern crate memoffset;
+///
+/// use epoch::sync::list::{Entry, IsElement};
+///
 /// struct A {
 ///     entry: Entry,
 ///     data: usize,
 /// }
 ///
-/// impl IsElement<A> for A {
+/// unsafe impl IsElement<A> for A {
 ///     fn entry_of(a: &A) -> &Entry {
-///         let entry_ptr = ((a as usize) + offset_of!(A, entry)) as *const Entry;
-///         unsafe { &*entry_ptr }
+///         &a.entry
 ///     }
 ///
-///     unsafe fn element_of(entry: &Entry) -> &T {
-///         let elem_ptr = ((entry as usize) - offset_of!(A, entry)) as *const T;
+///     unsafe fn element_of(entry: &Entry) -> &A {
+///         let elem_ptr = ((entry as *const Entry as usize) - offset_of!(A, entry)) as *const A;
 ///         &*elem_ptr
 ///     }
 ///
@@ -47,6 +120,7 @@ pub struct Entry {
 ///         drop(Box::from_raw(elem as *const A as *mut A));
 ///     }
 /// }
+/// # fn main() {}
 /// ```
 ///
 /// This trait is implemented on a type separate from `T` (although it can be just `T`), because
@@ -57,15 +131,33 @@ pub struct Entry {
 /// For example, we can insert the following struct into two lists using `entry1` for one
 /// and `entry2` for the other:
 ///
-/// ```ignore
+/// ```
+/// #[macro_use]
+/// extern crate crossbeam_epoch as epoch;
+///
+/// use epoch::sync::list::{Entry, List};
+///
 /// struct B {
 ///     entry1: Entry,
 ///     entry2: Entry,
 ///     data: usize,
 /// }
+///
+/// struct ByEntry1;
+/// struct ByEntry2;
+///
+/// impl_is_element!(ByEntry1 => B, entry1);
+/// impl_is_element!(ByEntry2 => B, entry2);
+///
+/// fn main() {
+///     let by_entry1: List<B, ByEntry1> = List::new();
+///     let by_entry2: List<B, ByEntry2> = List::new();
+/// #   drop((by_entry1, by_entry2));
+/// }
 /// ```
 ///
-pub trait IsElement<T> {
+/// [`impl_is_element!`]: ../../macro.impl_is_element.html
+pub unsafe trait IsElement<T> {
     /// Returns a reference to this element's `Entry`.
     fn entry_of(&T) -> &Entry;
 
@@ -92,16 +184,25 @@ pub trait IsElement<T> {
 }
 
 /// A lock-free, intrusive linked list of type `T`.
+///
+/// When the list is dropped, all elements remaining in it are finalized.
 #[derive(Debug)]
 pub struct List<T, C: IsElement<T> = T> {
     /// The head of the linked list.
     head: Atomic<Entry>,
 
+    /// The collector the list is bound to.
+    collector: AtomicUsize,
+
     /// The phantom data for using `T` and `C`.
     _marker: PhantomData<(T, C)>,
 }
 
 /// An iterator used for retrieving values from the list.
+///
+/// This struct is created by [`List::iter`].
+///
+/// [`List::iter`]: struct.List.html#method.iter
 pub struct Iter<'g, T: 'g, C: IsElement<T>> {
     /// The guard that protects the iteration.
     guard: &'g Guard,
@@ -146,13 +247,23 @@ impl Entry {
     pub unsafe fn delete(&self, guard: &Guard) {
         self.next.fetch_or(1, Release, guard);
     }
+
+    /// Returns `true` if this entry has been marked as deleted.
+    pub fn is_deleted(&self) -> bool {
+        self.next.load(Relaxed, unsafe { unprotected() }).tag() == 1
+    }
 }
 
+/// Computes field offsets in `impl_is_element!` without creating references to invalid data.
+#[doc(hidden)]
+pub use memoffset::offset_of as __offset_of;
+
 impl<T, C: IsElement<T>> List<T, C> {
     /// Returns a new, empty linked list.
     pub fn new() -> Self {
         Self {
             head: Atomic::null(),
+            collector: AtomicUsize::new(0),
             _marker: PhantomData,
         }
     }
@@ -166,8 +277,15 @@ impl<T, C: IsElement<T>> List<T, C> {
     /// - `container` is not null
     /// - `container` is immovable, e.g. inside a `Box`
     /// - the same `Entry` is not inserted more than once
-    /// - the inserted object will be removed before the list is dropped
+    /// - it is safe to call `C::finalize` on the inserted object once it is removed, or when the
+    ///   list is dropped
+    ///
+    /// # Panics
+    ///
+    /// Panics if `guard` belongs to a different collector than the list.
     pub unsafe fn insert<'g>(&'g self, container: Shared<'g, T>, guard: &'g Guard) {
+        bind_guard(&self.collector, guard);
+
         // Insert right after head, i.e. at the beginning of the list.
         let to = &self.head;
         // Get the intrusively stored Entry of the new element to insert.
@@ -202,7 +320,13 @@ impl<T, C: IsElement<T>> List<T, C> {
     /// 2. If an object is deleted during iteration, it may or may not be returned.
     /// 3. The iteration may be aborted when it lost in a race condition. In this case, the winning
     ///    thread will continue to iterate over the same list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `guard` belongs to a different collector than the list.
     pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, T, C> {
+        bind_guard(&self.collector, guard);
+
         Iter {
             guard,
             pred: &self.head,
@@ -213,6 +337,47 @@ impl<T, C: IsElement<T>> List<T, C> {
     }
 }
 
+impl<T: Send + 'static, C: IsElement<T>> List<T, C> {
+    /// Inserts `element` into the head of the list and returns a pointer to it.
+    ///
+    /// The element is owned by the list from now on. It is finalized some time after it is removed
+    /// from the list, or when the list is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `guard` belongs to a different collector than the list.
+    pub fn push<'g>(&'g self, element: Owned<T>, guard: &'g Guard) -> Shared<'g, T> {
+        let element = element.into_shared(guard);
+        // `element` is a fresh allocation, so its entry isn't a member of any list. It is
+        // deallocated by `C::finalize` just like an `Owned<T>` would be.
+        unsafe {
+            self.insert(element, guard);
+        }
+        element
+    }
+
+    /// Removes `element` from the list.
+    ///
+    /// The element is marked as deleted, so iterators will not return it anymore, and it will be
+    /// finalized once no thread can be referencing it.
+    ///
+    /// Returns `false` if the element was already removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `guard` belongs to a different collector than the list.
+    pub fn remove(&self, element: &T, guard: &Guard) -> bool {
+        bind_guard(&self.collector, guard);
+        C::entry_of(element).next.fetch_or(1, Release, guard).tag() == 0
+    }
+}
+
+impl<T, C: IsElement<T>> Default for List<T, C> {
+    fn default() -> Self {
+        List::new()
+    }
+}
+
 impl<T, C: IsElement<T>> Drop for List<T, C> {
     fn drop(&mut self) {
         unsafe {
@@ -220,11 +385,8 @@ impl<T, C: IsElement<T>> Drop for List<T, C> {
             let mut curr = self.head.load(Relaxed, guard);
             while let Some(c) = curr.as_ref() {
                 let succ = c.next.load(Relaxed, guard);
-                // Verify that all elements have been removed from the list.
-                assert_eq!(succ.tag(), 1);
-
                 C::finalize(curr.deref());
-                curr = succ;
+                curr = succ.with_tag(0);
             }
         }
     }
@@ -287,14 +449,21 @@ impl<'g, T: 'g, C: IsElement<T>> Iterator for Iter<'g, T, C> {
     }
 }
 
+impl<'g, T: 'g, C: IsElement<T>> fmt::Debug for Iter<'g, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Iter").finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use {Collector, Owned};
     use crossbeam_utils::thread;
     use std::sync::Barrier;
+    use std::sync::atomic::ATOMIC_USIZE_INIT;
     use super::*;
 
-    impl IsElement<Entry> for Entry {
+    unsafe impl IsElement<Entry> for Entry {
         fn entry_of(entry: &Entry) -> &Entry {
             entry
         }
@@ -385,6 +554,87 @@ mod tests {
         assert!(iter.next().is_none());
     }
 
+    struct Elem {
+        entry: Entry,
+        value: usize,
+    }
+
+    impl_is_element!(Elem, entry);
+
+    fn elem(value: usize) -> Owned<Elem> {
+        Owned::new(Elem { entry: Entry::default(), value })
+    }
+
+    /// Checks the safe interface for inserting and removing elements.
+    #[test]
+    fn push_remove() {
+        let collector = Collector::new();
+        let handle = collector.register();
+        let guard = &handle.pin();
+
+        let l: List<Elem> = List::new();
+        l.push(elem(1), guard);
+        let e2 = l.push(elem(2), guard);
+        l.push(elem(3), guard);
+
+        let values: Vec<usize> = l.iter(guard).map(|e| e.unwrap().value).collect();
+        assert_eq!(values, [3, 2, 1]);
+
+        let e2 = unsafe { e2.deref() };
+        assert!(l.remove(e2, guard));
+        assert!(e2.entry.is_deleted());
+        assert!(!l.remove(e2, guard));
+
+        let values: Vec<usize> = l.iter(guard).map(|e| e.unwrap().value).collect();
+        assert_eq!(values, [3, 1]);
+    }
+
+    /// Checks that dropping the list finalizes all elements, whether removed or not.
+    #[test]
+    fn drop_finalizes() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Counted {
+            entry: Entry,
+        }
+
+        impl_is_element!(Counted, entry);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Relaxed);
+            }
+        }
+
+        let collector = Collector::new();
+        let handle = collector.register();
+        let guard = &handle.pin();
+
+        let l: List<Counted> = List::new();
+        for i in 0..5 {
+            let e = l.push(Owned::new(Counted { entry: Entry::default() }), guard);
+            if i % 2 == 0 {
+                l.remove(unsafe { e.deref() }, guard);
+            }
+        }
+
+        drop(l);
+        assert_eq!(DROPS.load(Relaxed), 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_collector() {
+        let c1 = Collector::new();
+        let c2 = Collector::new();
+        let h1 = c1.register();
+        let h2 = c2.register();
+
+        let l: List<Elem> = List::new();
+        l.push(elem(1), &h1.pin());
+        l.iter(&h2.pin());
+    }
+
     const THREADS: usize = 8;
     const ITERS: usize = 512;
 
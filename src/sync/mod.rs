@@ -1,4 +1,49 @@
-//! Synchronization primitives.
+//! Concurrent data structures built on top of epoch-based garbage collection.
 
+pub mod arc;
+pub mod cell;
 pub mod list;
 pub mod queue;
+pub mod stack;
+
+pub use self::arc::{ArcRef, AtomicArc};
+pub use self::cell::EpochCell;
+pub use self::queue::Queue;
+pub use self::stack::Stack;
+
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+
+use collector::Collector;
+use guard::Guard;
+use internal::Global;
+
+/// Asserts that `guard` either belongs to `collector` or is unprotected.
+///
+/// Data structures tied to a collector call this before using a guard, because objects unlinked
+/// under a guard of another collector could be destroyed while still in use.
+#[inline]
+pub(crate) fn check_guard(collector: &Collector, guard: &Guard) {
+    if let Some(c) = guard.collector() {
+        assert!(c == collector, "guard belongs to a different collector");
+    }
+}
+
+/// Binds a data structure to the collector of `guard`, or asserts that it is already bound to it.
+///
+/// `bound` holds the address of the bound collector's global data, or zero if the data structure
+/// hasn't been used with a guard yet. Unprotected guards are always accepted.
+///
+/// This is the counterpart of `check_guard` for data structures that can't hold a `Collector`,
+/// e.g. because the collector itself contains them.
+#[inline]
+pub(crate) fn bind_guard(bound: &AtomicUsize, guard: &Guard) {
+    if let Some(c) = guard.collector() {
+        let global = &*c.global as *const Global as usize;
+        if bound.load(Relaxed) != global {
+            if let Err(previous) = bound.compare_exchange(0, global, Relaxed, Relaxed) {
+                assert_eq!(previous, global, "guard belongs to a different collector");
+            }
+        }
+    }
+}
@@ -5,19 +5,26 @@
 //! Michael and Scott.  Simple, Fast, and Practical Non-Blocking and Blocking Concurrent Queue
 //! Algorithms.  PODC 1996.  http://dl.acm.org/citation.cfm?id=248106
 
-use core::mem::{self, ManuallyDrop};
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
 use core::ptr;
+use core::sync::atomic::{self, AtomicUsize};
 use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
 
 use crossbeam_utils::CachePadded;
 
-use {unprotected, Atomic, Guard, Owned, Shared};
+use {unprotected, Atomic, Collector, Guard, Owned, Shared};
+use super::check_guard;
 
 // The representation here is a singly-linked list, with a sentinel node at the front. In general
 // the `tail` pointer may lag behind the actual tail. Non-sentinel nodes are either all `Data` or
 // all `Blocked` (requests for data from blocked threads).
+//
+// `RawQueue` is the bare algorithm. The caller is responsible for using guards of a single
+// collector with it. `Queue` wraps it into a safe interface.
 #[derive(Debug)]
-pub struct Queue<T> {
+pub(crate) struct RawQueue<T> {
     head: CachePadded<Atomic<Node<T>>>,
     tail: CachePadded<Atomic<Node<T>>>,
 }
@@ -26,29 +33,89 @@ pub struct Queue<T> {
 struct Node<T> {
     /// The slot in which a value of type `T` can be stored.
     ///
-    /// The type of `data` is `ManuallyDrop<T>` because a `Node<T>` doesn't always contain a `T`.
-    /// For example, the sentinel node in a queue never contains a value: its slot is always empty.
-    /// Other nodes start their life with a push operation and contain a value until it gets popped
-    /// out. After that such empty nodes get added to the collector for destruction.
-    data: ManuallyDrop<T>,
+    /// The type of `data` is `ManuallyDrop<Option<T>>` because a `Node<T>` doesn't always contain
+    /// a `T`. For example, the initial sentinel node in a queue never contains a value: its slot
+    /// is `None`. Other nodes start their life with a push operation and contain a value until it
+    /// gets popped out. After that such empty nodes get added to the collector for destruction.
+    data: ManuallyDrop<Option<T>>,
+
+    /// Synchronizes taking the value out of the node with iterators reading it.
+    ///
+    /// The lowest bit is `TAKEN`, and the rest counts the iterators currently reading the value.
+    state: AtomicUsize,
 
     next: Atomic<Node<T>>,
 }
 
+/// Set in the node's state once the value is being taken out by a pop.
+const TAKEN: usize = 1;
+
+/// Added to the node's state by an iterator reading the value.
+const READER: usize = 2;
+
 // Any particular `T` should never be accessed concurrently, so no need for `Sync`.
-unsafe impl<T: Send> Sync for Queue<T> {}
-unsafe impl<T: Send> Send for Queue<T> {}
+unsafe impl<T: Send> Sync for RawQueue<T> {}
+unsafe impl<T: Send> Send for RawQueue<T> {}
 
+impl<T> Node<T> {
+    /// Returns a reference to the value in a node that is not the initial sentinel.
+    ///
+    /// # Safety
+    ///
+    /// The value must not have been popped out of the node.
+    unsafe fn value(&self) -> &T {
+        match *self.data {
+            Some(ref value) => value,
+            None => unreachable!(),
+        }
+    }
 
-impl<T> Queue<T> {
+    /// Takes the value out of a node that has just become the sentinel.
+    ///
+    /// Waits until concurrent iterators are done reading the value.
+    ///
+    /// # Safety
+    ///
+    /// The value must not have been taken out of the node.
+    unsafe fn take(&self) -> Option<T> {
+        self.state.fetch_or(TAKEN, Acquire);
+        while self.state.load(Acquire) != TAKEN {
+            #[allow(deprecated)]
+            atomic::spin_loop_hint();
+        }
+        ManuallyDrop::into_inner(ptr::read(&self.data))
+    }
+
+    /// Clones the value in a node that is not the initial sentinel, unless it has been taken out.
+    fn clone_value(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        let state = self.state.fetch_add(READER, Acquire);
+
+        // A pop of this node waits for the reader to leave, even if the clone panics.
+        let _reader = scopeguard::guard((), |_| {
+            self.state.fetch_sub(READER, Release);
+        });
+
+        if state & TAKEN == 0 {
+            Some(unsafe { self.value() }.clone())
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> RawQueue<T> {
     /// Create a new, empty queue.
-    pub fn new() -> Queue<T> {
-        let q = Queue {
+    pub fn new() -> RawQueue<T> {
+        let q = RawQueue {
             head: CachePadded::new(Atomic::null()),
             tail: CachePadded::new(Atomic::null()),
         };
         let sentinel = Owned::new(Node {
-            data: unsafe { mem::uninitialized() },
+            data: ManuallyDrop::new(None),
+            state: AtomicUsize::new(0),
             next: Atomic::null(),
         });
         unsafe {
@@ -87,7 +154,8 @@ impl<T> Queue<T> {
     /// Adds `t` to the back of the queue, possibly waking up threads blocked on `pop`.
     pub fn push(&self, t: T, guard: &Guard) {
         let new = Owned::new(Node {
-            data: ManuallyDrop::new(t),
+            data: ManuallyDrop::new(Some(t)),
+            state: AtomicUsize::new(0),
             next: Atomic::null(),
         });
         let new = Owned::into_shared(new, guard);
@@ -114,8 +182,8 @@ impl<T> Queue<T> {
                 self.head
                     .compare_and_set(head, next, Release, guard)
                     .map(|_| {
-                        guard.defer_destroy(head);
-                        Some(ManuallyDrop::into_inner(ptr::read(&n.data)))
+                        guard.defer_unchecked(move || head.into_owned());
+                        n.take()
                     })
                     .map_err(|_| ())
             },
@@ -123,31 +191,6 @@ impl<T> Queue<T> {
         }
     }
 
-    /// Attempts to pop a data node, if the data satisfies the given condition. `Ok(None)` if queue
-    /// is empty or the data does not satisfy the condition; `Err(())` if lost race to pop.
-    #[inline(always)]
-    fn pop_if_internal<F>(&self, condition: F, guard: &Guard) -> Result<Option<T>, ()>
-    where
-        T: Sync,
-        F: Fn(&T) -> bool,
-    {
-        let head = self.head.load(Acquire, guard);
-        let h = unsafe { head.deref() };
-        let next = h.next.load(Acquire, guard);
-        match unsafe { next.as_ref() } {
-            Some(n) if condition(&n.data) => unsafe {
-                self.head
-                    .compare_and_set(head, next, Release, guard)
-                    .map(|_| {
-                        guard.defer_destroy(head);
-                        Some(ManuallyDrop::into_inner(ptr::read(&n.data)))
-                    })
-                    .map_err(|_| ())
-            },
-            None | Some(_) => Ok(None),
-        }
-    }
-
     /// Attempts to dequeue from the front.
     ///
     /// Returns `None` if the queue is observed to be empty.
@@ -158,25 +201,9 @@ impl<T> Queue<T> {
             }
         }
     }
-
-    /// Attempts to dequeue from the front, if the item satisfies the given condition.
-    ///
-    /// Returns `None` if the queue is observed to be empty, or the head does not satisfy the given
-    /// condition.
-    pub fn try_pop_if<F>(&self, condition: F, guard: &Guard) -> Option<T>
-    where
-        T: Sync,
-        F: Fn(&T) -> bool,
-    {
-        loop {
-            if let Ok(head) = self.pop_if_internal(&condition, guard) {
-                return head;
-            }
-        }
-    }
 }
 
-impl<T> Drop for Queue<T> {
+impl<T> Drop for RawQueue<T> {
     fn drop(&mut self) {
         unsafe {
             let guard = &unprotected();
@@ -190,20 +217,347 @@ impl<T> Drop for Queue<T> {
     }
 }
 
+/// A lock-free multi-producer multi-consumer queue.
+///
+/// The queue is tied to a [`Collector`], and every guard passed to its methods must belong to that
+/// collector. Queues created with [`new`] use the default collector, so guards obtained from
+/// [`pin`] can be used with them.
+///
+/// # Panics
+///
+/// Methods taking a guard panic if the guard belongs to a different collector.
+///
+/// # Progress
+///
+/// Pushing and popping are lock-free, except that popping an element waits for concurrent
+/// iterators created by [`iter`] to finish cloning it.
+///
+/// # Examples
+///
+/// ```
+/// use crossbeam_epoch::{self as epoch, sync::Queue};
+///
+/// let queue = Queue::new();
+///
+/// let guard = &epoch::pin();
+/// queue.push(1, guard);
+/// queue.push(2, guard);
+/// assert_eq!(queue.len(), 2);
+///
+/// assert_eq!(queue.try_pop(guard), Some(1));
+/// assert_eq!(queue.try_pop(guard), Some(2));
+/// assert_eq!(queue.try_pop(guard), None);
+/// ```
+///
+/// [`Collector`]: ../../struct.Collector.html
+/// [`new`]: struct.Queue.html#method.new
+/// [`pin`]: ../../fn.pin.html
+/// [`iter`]: struct.Queue.html#method.iter
+pub struct Queue<T> {
+    raw: RawQueue<T>,
+
+    /// The number of pushed elements that haven't been popped yet.
+    ///
+    /// It is incremented before an element is pushed and decremented after it is popped, so it
+    /// may overestimate the length but never underflows.
+    len: CachePadded<AtomicUsize>,
+
+    collector: Collector,
+}
+
+impl<T> Queue<T> {
+    /// Creates a new, empty queue using the default collector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::sync::Queue;
+    ///
+    /// let queue = Queue::<i32>::new();
+    /// assert!(queue.is_empty());
+    /// ```
+    #[cfg(feature = "use_std")]
+    pub fn new() -> Queue<T> {
+        Self::with_collector(::default_collector().clone())
+    }
+
+    /// Creates a new, empty queue using the given collector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{sync::Queue, Collector};
+    ///
+    /// let collector = Collector::new();
+    /// let queue = Queue::with_collector(collector.clone());
+    ///
+    /// let handle = collector.register();
+    /// queue.push("hello", &handle.pin());
+    /// ```
+    pub fn with_collector(collector: Collector) -> Queue<T> {
+        Queue {
+            raw: RawQueue::new(),
+            len: CachePadded::new(AtomicUsize::new(0)),
+            collector,
+        }
+    }
+
+    /// Returns the collector the queue is tied to.
+    pub fn collector(&self) -> &Collector {
+        &self.collector
+    }
+
+    /// Adds `value` to the back of the queue.
+    pub fn push(&self, value: T, guard: &Guard) {
+        check_guard(&self.collector, guard);
+        self.len.fetch_add(1, Relaxed);
+        self.raw.push(value, guard);
+    }
+
+    /// Removes an element from the front of the queue and returns it.
+    ///
+    /// Returns `None` if the queue is observed to be empty.
+    ///
+    /// If the element is being cloned by a concurrent [`iter`], this waits for the clone to
+    /// finish.
+    ///
+    /// [`iter`]: struct.Queue.html#method.iter
+    pub fn try_pop(&self, guard: &Guard) -> Option<T> {
+        check_guard(&self.collector, guard);
+        let value = self.raw.try_pop(guard);
+        if value.is_some() {
+            self.len.fetch_sub(1, Relaxed);
+        }
+        value
+    }
+
+    /// Returns an estimate of the number of elements in the queue.
+    ///
+    /// If the queue is concurrently modified, the result may be larger than the number of elements
+    /// the queue contains at any single point in time.
+    pub fn len(&self) -> usize {
+        self.len.load(Relaxed)
+    }
+
+    /// Returns `true` if the queue is estimated to be empty.
+    ///
+    /// This is equivalent to `self.len() == 0`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over clones of the elements of the queue, from front to back.
+    ///
+    /// The iterator is weakly consistent: it never yields an element twice, and yields every
+    /// element that stays in the queue for the whole iteration, but elements pushed or popped
+    /// concurrently may or may not be yielded.
+    ///
+    /// Elements are cloned because they may be popped out of the queue concurrently. A pop of an
+    /// element that is being cloned waits for the clone to finish, so popping is not lock-free
+    /// while the queue is being iterated over.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, sync::Queue};
+    ///
+    /// let queue = Queue::new();
+    /// let guard = &epoch::pin();
+    /// queue.push(1, guard);
+    /// queue.push(2, guard);
+    ///
+    /// let v: Vec<_> = queue.iter(guard).collect();
+    /// assert_eq!(v, [1, 2]);
+    /// ```
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, T>
+    where
+        T: Clone + Sync,
+    {
+        check_guard(&self.collector, guard);
+        let head = self.raw.head.load(Acquire, guard);
+        Iter {
+            next: unsafe { head.deref() }.next.load(Acquire, guard),
+            guard,
+        }
+    }
+
+    /// Returns an iterator over mutable references to the elements of the queue, from front to
+    /// back.
+    ///
+    /// Because the queue is borrowed mutably, no other thread can modify it during the iteration,
+    /// so no guard is needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, sync::Queue};
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.push(1, &epoch::pin());
+    /// queue.push(2, &epoch::pin());
+    ///
+    /// for value in queue.iter_mut() {
+    ///     *value *= 10;
+    /// }
+    /// assert_eq!(queue.iter(&epoch::pin()).collect::<Vec<_>>(), [10, 20]);
+    /// ```
+    pub fn iter_mut<'a>(&'a mut self) -> IterMut<'a, T> {
+        // Nodes can't be unlinked while the queue is borrowed mutably, so no guard is needed.
+        let guard = unsafe { &unprotected() };
+        let head = self.raw.head.load(Acquire, guard);
+        IterMut {
+            next: unsafe { head.deref() }.next.load(Acquire, guard).as_raw() as *mut Node<T>,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns an iterator that pops elements from the front of the queue until it is observed to
+    /// be empty.
+    ///
+    /// Elements pushed concurrently may or may not be yielded by the iterator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, sync::Queue};
+    ///
+    /// let queue = Queue::new();
+    /// let guard = &epoch::pin();
+    /// queue.push(1, guard);
+    /// queue.push(2, guard);
+    ///
+    /// let v: Vec<_> = queue.drain(guard).collect();
+    /// assert_eq!(v, [1, 2]);
+    /// assert!(queue.is_empty());
+    /// ```
+    pub fn drain<'a>(&'a self, guard: &'a Guard) -> Drain<'a, T> {
+        check_guard(&self.collector, guard);
+        Drain { queue: self, guard }
+    }
+}
+
+#[cfg(feature = "use_std")]
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+impl<T> fmt::Debug for Queue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Queue").field("len", &self.len()).finish()
+    }
+}
+
+/// An iterator over clones of the elements of a [`Queue`].
+///
+/// This struct is created by [`Queue::iter`].
+///
+/// [`Queue`]: struct.Queue.html
+/// [`Queue::iter`]: struct.Queue.html#method.iter
+pub struct Iter<'g, T: 'g> {
+    next: Shared<'g, Node<T>>,
+    guard: &'g Guard,
+}
+
+impl<'g, T: Clone + Sync> Iterator for Iter<'g, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        // Nodes reachable from the head stay allocated while the guard is pinned. Nodes that have
+        // been popped since are still linked to the rest of the queue, but their values are
+        // skipped.
+        while let Some(node) = unsafe { self.next.as_ref() } {
+            self.next = node.next.load(Acquire, self.guard);
+            if let Some(value) = node.clone_value() {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+impl<'g, T> fmt::Debug for Iter<'g, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Iter").finish()
+    }
+}
+
+/// An iterator over mutable references to the elements of a [`Queue`].
+///
+/// This struct is created by [`Queue::iter_mut`].
+///
+/// [`Queue`]: struct.Queue.html
+/// [`Queue::iter_mut`]: struct.Queue.html#method.iter_mut
+pub struct IterMut<'a, T: 'a> {
+    next: *mut Node<T>,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        unsafe {
+            self.next.as_mut().map(|node| {
+                let guard = &unprotected();
+                self.next = node.next.load(Acquire, guard).as_raw() as *mut Node<T>;
+                match *node.data {
+                    Some(ref mut value) => value,
+                    None => unreachable!(),
+                }
+            })
+        }
+    }
+}
+
+impl<'a, T> fmt::Debug for IterMut<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IterMut").finish()
+    }
+}
+
+/// A draining iterator for a [`Queue`].
+///
+/// This struct is created by [`Queue::drain`].
+///
+/// [`Queue`]: struct.Queue.html
+/// [`Queue::drain`]: struct.Queue.html#method.drain
+pub struct Drain<'a, T: 'a> {
+    queue: &'a Queue<T>,
+    guard: &'a Guard,
+}
+
+impl<'a, T> Iterator for Drain<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.queue.try_pop(self.guard)
+    }
+}
+
+impl<'a, T> fmt::Debug for Drain<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Drain").finish()
+    }
+}
+
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crossbeam_utils::thread;
+    use std::panic;
+    use std::sync::atomic::ATOMIC_USIZE_INIT;
     use pin;
 
     struct Queue<T> {
-        queue: super::Queue<T>,
+        queue: RawQueue<T>,
     }
 
     impl<T> Queue<T> {
         pub fn new() -> Queue<T> {
-            Queue { queue: super::Queue::new() }
+            Queue { queue: RawQueue::new() }
         }
 
         pub fn push(&self, t: T) {
@@ -425,4 +779,169 @@ mod test {
         assert!(!q.is_empty());
         assert!(q.try_pop().is_some());
     }
+
+    #[test]
+    fn public_len() {
+        let q = super::Queue::new();
+        let guard = &pin();
+        assert!(q.is_empty());
+        for i in 0..10 {
+            q.push(i, guard);
+        }
+        assert_eq!(q.len(), 10);
+        assert_eq!(q.try_pop(guard), Some(0));
+        assert_eq!(q.len(), 9);
+        assert!(!q.is_empty());
+    }
+
+    #[test]
+    fn public_iter() {
+        let q = super::Queue::new();
+        let guard = &pin();
+        assert_eq!(q.iter(guard).count(), 0);
+        for i in 0..5 {
+            q.push(i, guard);
+        }
+        q.try_pop(guard);
+        assert_eq!(q.iter(guard).collect::<Vec<_>>(), [1, 2, 3, 4]);
+
+        // Elements popped during the iteration are skipped.
+        let mut iter = q.iter(guard);
+        assert_eq!(iter.next(), Some(1));
+        q.try_pop(guard);
+        q.try_pop(guard);
+        q.push(5, guard);
+        assert_eq!(iter.collect::<Vec<_>>(), [3, 4, 5]);
+    }
+
+    #[test]
+    fn public_iter_concurrent() {
+        const COUNT: usize = 10_000;
+
+        let q = super::Queue::new();
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for i in 0..COUNT {
+                    let guard = &pin();
+                    q.push(vec![i], guard);
+                    q.try_pop(guard);
+                }
+            });
+            for _ in 0..COUNT / 100 {
+                let guard = &pin();
+                let mut prev = None;
+                for v in q.iter(guard) {
+                    assert!(prev < Some(v[0]));
+                    prev = Some(v[0]);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn public_iter_clone_panics() {
+        struct Elem;
+
+        impl Clone for Elem {
+            fn clone(&self) -> Elem {
+                panic!("clone");
+            }
+        }
+
+        let q = super::Queue::new();
+        let guard = &pin();
+        q.push(Elem, guard);
+
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(|| q.iter(guard).next()));
+        assert!(res.is_err());
+
+        // The panicking reader has left, so popping doesn't wait for it.
+        assert!(q.try_pop(guard).is_some());
+    }
+
+    #[test]
+    fn public_iter_mut() {
+        let mut q = super::Queue::new();
+        assert_eq!(q.iter_mut().count(), 0);
+        {
+            let guard = &pin();
+            for i in 0..5 {
+                q.push(i, guard);
+            }
+            q.try_pop(guard);
+        }
+        for v in q.iter_mut() {
+            *v *= 10;
+        }
+        assert_eq!(q.iter(&pin()).collect::<Vec<_>>(), [10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn public_drain() {
+        let q = super::Queue::new();
+        let guard = &pin();
+        for i in 0..5 {
+            q.push(i, guard);
+        }
+        assert_eq!(q.drain(guard).collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+        assert!(q.is_empty());
+        assert_eq!(q.try_pop(guard), None);
+    }
+
+    #[test]
+    fn public_drop_elements() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem;
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Relaxed);
+            }
+        }
+
+        let q = super::Queue::new();
+        {
+            let guard = &pin();
+            for _ in 0..10 {
+                q.push(Elem, guard);
+            }
+            drop(q.try_pop(guard));
+        }
+        assert_eq!(DROPS.load(Relaxed), 1);
+        drop(q);
+        assert_eq!(DROPS.load(Relaxed), 10);
+    }
+
+    #[test]
+    fn public_mpmc() {
+        const COUNT: usize = 10_000;
+        const THREADS: usize = 4;
+
+        let q = super::Queue::new();
+        let popped = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| for i in 0..COUNT {
+                    q.push(i, &pin());
+                });
+                scope.spawn(|| {
+                    while popped.load(Relaxed) < COUNT * THREADS {
+                        if q.try_pop(&pin()).is_some() {
+                            popped.fetch_add(1, Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn public_wrong_collector() {
+        let q = super::Queue::with_collector(Collector::new());
+        q.push(1, &pin());
+    }
 }
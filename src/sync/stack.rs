@@ -0,0 +1,431 @@
+//! Treiber's lock-free stack.
+//!
+//! Usable with any number of producers and consumers.
+//!
+//! Treiber.  Systems Programming: Coping with Parallelism.  IBM Almaden Research Center, 1986.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ptr;
+use core::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release};
+
+use {unprotected, Atomic, Collector, Guard, Owned, Shared};
+use super::check_guard;
+
+/// A lock-free multi-producer multi-consumer stack.
+///
+/// The stack is tied to a [`Collector`], and every guard passed to its methods must belong to that
+/// collector. Stacks created with [`new`] use the default collector, so guards obtained from
+/// [`pin`] can be used with them.
+///
+/// # Panics
+///
+/// Methods taking a guard panic if the guard belongs to a different collector.
+///
+/// # Examples
+///
+/// ```
+/// use crossbeam_epoch::{self as epoch, sync::Stack};
+///
+/// let stack = Stack::new();
+///
+/// let guard = &epoch::pin();
+/// stack.push(1, guard);
+/// stack.push(2, guard);
+///
+/// assert_eq!(stack.pop(guard), Some(2));
+/// assert_eq!(stack.pop(guard), Some(1));
+/// assert_eq!(stack.pop(guard), None);
+/// ```
+///
+/// [`Collector`]: ../../struct.Collector.html
+/// [`new`]: struct.Stack.html#method.new
+/// [`pin`]: ../../fn.pin.html
+pub struct Stack<T> {
+    head: Atomic<Node<T>>,
+    collector: Collector,
+}
+
+struct Node<T> {
+    /// The value in the node.
+    ///
+    /// The type of `data` is `ManuallyDrop<T>` because the value is moved out of the node when it
+    /// gets popped, and only the empty node is then added to the collector for destruction.
+    data: ManuallyDrop<T>,
+
+    next: Atomic<Node<T>>,
+}
+
+// Any particular `T` should never be accessed concurrently, so no need for `Sync`.
+unsafe impl<T: Send> Sync for Stack<T> {}
+unsafe impl<T: Send> Send for Stack<T> {}
+
+impl<T> Stack<T> {
+    /// Creates a new, empty stack using the default collector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::sync::Stack;
+    ///
+    /// let stack = Stack::<i32>::new();
+    /// assert!(stack.is_empty());
+    /// ```
+    #[cfg(feature = "use_std")]
+    pub fn new() -> Stack<T> {
+        Self::with_collector(::default_collector().clone())
+    }
+
+    /// Creates a new, empty stack using the given collector.
+    pub fn with_collector(collector: Collector) -> Stack<T> {
+        Stack {
+            head: Atomic::null(),
+            collector,
+        }
+    }
+
+    /// Returns the collector the stack is tied to.
+    pub fn collector(&self) -> &Collector {
+        &self.collector
+    }
+
+    /// Pushes `value` on top of the stack.
+    pub fn push(&self, value: T, guard: &Guard) {
+        check_guard(&self.collector, guard);
+
+        let node = Owned::new(Node {
+            data: ManuallyDrop::new(value),
+            next: Atomic::null(),
+        });
+
+        let res = self.head.fetch_update_owned(node, Release, guard, |head, node| {
+            node.next.store(head, Relaxed);
+            true
+        });
+        debug_assert!(res.is_ok());
+    }
+
+    /// Removes the value on top of the stack and returns it.
+    ///
+    /// Returns `None` if the stack is observed to be empty.
+    pub fn pop(&self, guard: &Guard) -> Option<T> {
+        check_guard(&self.collector, guard);
+
+        let mut head = self.head.load(Acquire, guard);
+        loop {
+            match unsafe { head.as_ref() } {
+                None => return None,
+                Some(h) => {
+                    let next = h.next.load(Relaxed, guard);
+                    // On failure, the new head is dereferenced in the next iteration, so it
+                    // must be loaded with `Acquire`.
+                    match self.head.compare_and_set_weak(head, next, (AcqRel, Acquire), guard) {
+                        Ok(_) => unsafe {
+                            // An unprotected guard destroys the node right away, so the value has
+                            // to be read out first.
+                            let data = ManuallyDrop::into_inner(ptr::read(&h.data));
+                            guard.defer_destroy(head);
+                            return Some(data);
+                        },
+                        Err(err) => head = err.current,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns a reference to the value on top of the stack.
+    ///
+    /// The value may be concurrently popped by another thread, in which case the reference keeps
+    /// pointing to a bitwise copy of it until the guard is dropped. This is only sound for types
+    /// without destructors, hence the `T: Copy` bound.
+    ///
+    /// Returns `None` if the stack is observed to be empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, sync::Stack};
+    ///
+    /// let stack = Stack::new();
+    /// let guard = &epoch::pin();
+    /// assert_eq!(stack.peek(guard), None);
+    ///
+    /// stack.push(7, guard);
+    /// assert_eq!(stack.peek(guard), Some(&7));
+    /// assert_eq!(stack.pop(guard), Some(7));
+    /// ```
+    pub fn peek<'g>(&self, guard: &'g Guard) -> Option<&'g T>
+    where
+        T: Copy + Sync,
+    {
+        check_guard(&self.collector, guard);
+
+        let head = self.head.load(Acquire, guard);
+        unsafe { head.as_ref().map(|h| &*h.data) }
+    }
+
+    /// Returns `true` if the stack is observed to be empty.
+    pub fn is_empty(&self) -> bool {
+        // The pointer is only compared against null, so no guard is needed.
+        self.head.load(Acquire, unsafe { unprotected() }).is_null()
+    }
+
+    /// Atomically removes all values from the stack and returns an iterator over them, from top to
+    /// bottom.
+    ///
+    /// Values that are not consumed by the iterator are dropped when the iterator is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, sync::Stack};
+    ///
+    /// let stack = Stack::new();
+    /// let guard = &epoch::pin();
+    /// for i in 0..3 {
+    ///     stack.push(i, guard);
+    /// }
+    ///
+    /// let v: Vec<_> = stack.take_all(guard).collect();
+    /// assert_eq!(v, [2, 1, 0]);
+    /// assert!(stack.is_empty());
+    /// ```
+    pub fn take_all<'g>(&self, guard: &'g Guard) -> TakeAll<'g, T> {
+        check_guard(&self.collector, guard);
+
+        let head = self.head.swap(Shared::null(), Acquire, guard);
+        TakeAll {
+            next: head,
+            guard,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for Stack<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let guard = unprotected();
+            drop(TakeAll::<T> {
+                next: self.head.load(Relaxed, guard),
+                guard,
+                _marker: PhantomData,
+            });
+        }
+    }
+}
+
+#[cfg(feature = "use_std")]
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Stack::new()
+    }
+}
+
+impl<T> fmt::Debug for Stack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Stack").finish()
+    }
+}
+
+/// An iterator over the values removed from a [`Stack`] at once.
+///
+/// This struct is created by [`Stack::take_all`].
+///
+/// [`Stack`]: struct.Stack.html
+/// [`Stack::take_all`]: struct.Stack.html#method.take_all
+pub struct TakeAll<'g, T: 'g> {
+    /// The next node in the chain of removed nodes.
+    next: Shared<'g, Node<T>>,
+
+    /// The guard used for deferring destruction of the nodes, since other threads may still be
+    /// reading them.
+    guard: &'g Guard,
+
+    _marker: PhantomData<T>,
+}
+
+impl<'g, T> Iterator for TakeAll<'g, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        unsafe {
+            self.next.as_ref().map(|n| {
+                let node = self.next;
+                self.next = n.next.load(Relaxed, self.guard);
+                let data = ManuallyDrop::into_inner(ptr::read(&n.data));
+                // An unprotected guard destroys the node right away, as `Stack::drop` does.
+                self.guard.defer_destroy(node);
+                data
+            })
+        }
+    }
+}
+
+impl<'g, T> Drop for TakeAll<'g, T> {
+    fn drop(&mut self) {
+        for _ in self {}
+    }
+}
+
+impl<'g, T> fmt::Debug for TakeAll<'g, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TakeAll").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    use std::sync::atomic::Ordering;
+
+    use crossbeam_utils::thread;
+
+    use {pin, unprotected, Collector};
+    use super::Stack;
+
+    #[test]
+    fn push_pop() {
+        let stack = Stack::new();
+        let guard = &pin();
+        assert!(stack.is_empty());
+
+        for i in 0..10 {
+            stack.push(i, guard);
+        }
+        assert!(!stack.is_empty());
+        assert_eq!(stack.peek(guard), Some(&9));
+
+        for i in (0..10).rev() {
+            assert_eq!(stack.pop(guard), Some(i));
+        }
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(guard), None);
+        assert_eq!(stack.peek(guard), None);
+    }
+
+    #[test]
+    fn take_all() {
+        let stack = Stack::new();
+        let guard = &pin();
+        assert_eq!(stack.take_all(guard).count(), 0);
+
+        for i in 0..5 {
+            stack.push(i, guard);
+        }
+        let mut all = stack.take_all(guard);
+        assert!(stack.is_empty());
+        stack.push(5, guard);
+
+        assert_eq!(all.next(), Some(4));
+        assert_eq!(all.collect::<Vec<_>>(), [3, 2, 1, 0]);
+        assert_eq!(stack.pop(guard), Some(5));
+    }
+
+    #[test]
+    fn drops() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem;
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let stack = Stack::new();
+        {
+            let guard = &pin();
+            for _ in 0..10 {
+                stack.push(Elem, guard);
+            }
+            drop(stack.pop(guard));
+            assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+            let mut all = stack.take_all(guard);
+            drop(all.next());
+            assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+
+            // Dropping the iterator drops the remaining values.
+            drop(all);
+            assert_eq!(DROPS.load(Ordering::Relaxed), 10);
+
+            for _ in 0..5 {
+                stack.push(Elem, guard);
+            }
+        }
+        drop(stack);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 15);
+    }
+
+    #[test]
+    fn drops_heap_values() {
+        let stack = Stack::new();
+        {
+            let guard = &pin();
+            for i in 0..10 {
+                stack.push(vec![i; 16], guard);
+            }
+            assert_eq!(stack.pop(guard), Some(vec![9; 16]));
+
+            let mut all = stack.take_all(guard);
+            assert_eq!(all.next(), Some(vec![8; 16]));
+
+            for i in 0..5 {
+                stack.push(vec![i; 16], guard);
+            }
+        }
+
+        // Dropping the stack reads the remaining values with an unprotected guard.
+        drop(stack);
+
+        let stack = Stack::new();
+        unsafe {
+            let guard = &unprotected();
+            for i in 0..10 {
+                stack.push(vec![i; 16], guard);
+            }
+            assert_eq!(stack.pop(guard), Some(vec![9; 16]));
+            assert_eq!(stack.take_all(guard).next(), Some(vec![8; 16]));
+        }
+    }
+
+    #[test]
+    fn mpmc() {
+        const COUNT: usize = 10_000;
+        const THREADS: usize = 4;
+
+        let stack = Stack::new();
+        let popped = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| for i in 0..COUNT {
+                    stack.push(i, &pin());
+                });
+                scope.spawn(|| {
+                    while popped.load(Ordering::Relaxed) < COUNT * THREADS {
+                        let guard = &pin();
+                        if stack.pop(guard).is_some() {
+                            popped.fetch_add(1, Ordering::Relaxed);
+                        } else {
+                            let taken = stack.take_all(guard).count();
+                            popped.fetch_add(taken, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_collector() {
+        let stack = Stack::with_collector(Collector::new());
+        stack.push(1, &pin());
+    }
+}
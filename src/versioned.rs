@@ -0,0 +1,708 @@
+//! Atomic pointers paired with a version counter.
+//!
+//! Epoch-based reclamation guarantees that an object is not freed while a thread might still be
+//! reading it, but not that a pointer doesn't change and change back between a load and a
+//! compare-and-set. Data structures recycling their nodes, like free lists and object pools,
+//! suffer from this ABA problem. Pairing the pointer with a counter that is incremented on every
+//! update makes such a compare-and-set fail.
+//!
+//! The pointer and the counter are updated together using a double-width compare-and-swap. With
+//! the `dwcas` feature, `cmpxchg16b` is used on x86-64 if the processor supports it, and
+//! `casp` or an exclusive load/store pair on AArch64. Otherwise, the pair is protected by a lock
+//! from a small global table.
+//!
+//! The `dwcas` feature requires Rust 1.69, the first version that can detect `cmpxchg16b` support.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering;
+
+use atomic::{CompareAndSetOrdering, Owned, Pointable, Pointer, Shared};
+use guard::{unprotected, Guard};
+
+/// An atomic pointer paired with a version counter that is incremented on every update.
+///
+/// This type has the same interface as [`Atomic`], except that loads return a [`Versioned`]
+/// pointer, which a compare-and-set expects as the current value. A compare-and-set succeeds only
+/// if neither the pointer nor the version has changed since the load, so it is not fooled by a
+/// node that was removed and inserted back in the meantime.
+///
+/// The pointer can be tagged just like an [`Atomic`].
+///
+/// # Examples
+///
+/// ```
+/// use crossbeam_epoch::{self as epoch, AtomicVersioned, Owned};
+/// use std::sync::atomic::Ordering::SeqCst;
+///
+/// let a = AtomicVersioned::new(1);
+/// let guard = &epoch::pin();
+///
+/// let old = a.load(SeqCst, guard);
+/// assert_eq!(old.version(), 0);
+///
+/// // Swap the pointer away and back again.
+/// let p = a.swap(Owned::new(2), SeqCst, guard);
+/// a.store(p.ptr(), SeqCst);
+///
+/// // The pointer is the same, but the version isn't.
+/// assert_eq!(a.load(SeqCst, guard).ptr(), old.ptr());
+/// assert!(a.compare_and_set(old, Owned::new(3), SeqCst, guard).is_err());
+/// # unsafe { drop(a.load(SeqCst, guard).ptr().into_owned()); } // avoid leak
+/// ```
+///
+/// [`Atomic`]: struct.Atomic.html
+/// [`Versioned`]: struct.Versioned.html
+#[repr(C, align(16))]
+pub struct AtomicVersioned<T: ?Sized + Pointable> {
+    /// The tagged pointer and the version.
+    data: UnsafeCell<[usize; 2]>,
+    _marker: PhantomData<*mut T>,
+}
+
+unsafe impl<T: ?Sized + Pointable + Send + Sync> Send for AtomicVersioned<T> {}
+unsafe impl<T: ?Sized + Pointable + Send + Sync> Sync for AtomicVersioned<T> {}
+
+impl<T> AtomicVersioned<T> {
+    /// Allocates `value` on the heap and returns a new versioned pointer pointing to it.
+    ///
+    /// The version starts at zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::AtomicVersioned;
+    ///
+    /// let a = AtomicVersioned::new(1234);
+    /// ```
+    pub fn new(value: T) -> AtomicVersioned<T> {
+        Self::from(Owned::new(value))
+    }
+}
+
+impl<T: ?Sized + Pointable> AtomicVersioned<T> {
+    /// Returns a new versioned pointer pointing to the tagged pointer `data`.
+    fn from_usize(data: usize) -> Self {
+        AtomicVersioned {
+            data: UnsafeCell::new([data, 0]),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a new null versioned pointer.
+    ///
+    /// The version starts at zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::AtomicVersioned;
+    ///
+    /// let a = AtomicVersioned::<i32>::null();
+    /// ```
+    pub fn null() -> AtomicVersioned<T> {
+        Self::from_usize(0)
+    }
+
+    /// Allocates an object initialized with `init` and returns a new versioned pointer pointing
+    /// to it.
+    ///
+    /// The version starts at zero.
+    pub fn init(init: T::Init) -> AtomicVersioned<T> {
+        Self::from(Owned::init(init))
+    }
+
+    /// Loads the pointer together with its version.
+    ///
+    /// This method takes an [`Ordering`] argument which describes the memory ordering of this
+    /// operation.
+    ///
+    /// [`Ordering`]: https://doc.rust-lang.org/std/sync/atomic/enum.Ordering.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, AtomicVersioned};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let a = AtomicVersioned::new(1234);
+    /// let guard = &epoch::pin();
+    /// let v = a.load(SeqCst, guard);
+    /// assert_eq!(unsafe { v.ptr().deref() }, &1234);
+    /// assert_eq!(v.version(), 0);
+    /// # unsafe { drop(v.ptr().into_owned()); } // avoid leak
+    /// ```
+    pub fn load<'g>(&self, ord: Ordering, _: &'g Guard) -> Versioned<'g, T> {
+        unsafe { Versioned::from_data(dw::load(self.data.get(), ord)) }
+    }
+
+    /// Stores a `Shared` or `Owned` pointer into the versioned pointer, incrementing the version.
+    ///
+    /// This method takes an [`Ordering`] argument which describes the memory ordering of this
+    /// operation.
+    ///
+    /// [`Ordering`]: https://doc.rust-lang.org/std/sync/atomic/enum.Ordering.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, AtomicVersioned, Owned, Shared};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let a = AtomicVersioned::<i32>::null();
+    /// a.store(Shared::null(), SeqCst);
+    /// assert_eq!(a.load(SeqCst, &epoch::pin()).version(), 1);
+    /// ```
+    pub fn store<P: Pointer<T>>(&self, new: P, ord: Ordering) {
+        self.swap(new, ord, unsafe { unprotected() });
+    }
+
+    /// Stores a `Shared` or `Owned` pointer into the versioned pointer, incrementing the version,
+    /// and returns the previous pointer together with its version.
+    ///
+    /// This method takes an [`Ordering`] argument which describes the memory ordering of this
+    /// operation.
+    ///
+    /// [`Ordering`]: https://doc.rust-lang.org/std/sync/atomic/enum.Ordering.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, AtomicVersioned, Shared};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let a = AtomicVersioned::new(1234);
+    /// let guard = &epoch::pin();
+    /// let p = a.swap(Shared::null(), SeqCst, guard);
+    /// assert_eq!(p.version(), 0);
+    /// assert_eq!(a.load(SeqCst, guard).version(), 1);
+    /// # unsafe { drop(p.ptr().into_owned()); } // avoid leak
+    /// ```
+    pub fn swap<'g, P>(&self, new: P, ord: Ordering, _: &'g Guard) -> Versioned<'g, T>
+    where
+        P: Pointer<T>,
+    {
+        let new = new.into_usize();
+        let mut current = unsafe { dw::load(self.data.get(), Ordering::Relaxed) };
+        loop {
+            let next = [new, current[1].wrapping_add(1)];
+            match unsafe { dw::compare_exchange(self.data.get(), current, next, ord, ord) } {
+                Ok(_) => return unsafe { Versioned::from_data(current) },
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Stores the pointer `new` (either `Shared` or `Owned`) into the versioned pointer if both
+    /// the current pointer and version are the same as in `current`, and increments the version.
+    /// The tag is also taken into account, so two pointers to the same object, but with different
+    /// tags, will not be considered equal.
+    ///
+    /// The return value is a result indicating whether the new pointer was written. On success the
+    /// pointer that was written is returned together with its version. On failure the actual
+    /// current value and `new` are returned.
+    ///
+    /// This method takes a [`CompareAndSetOrdering`] argument which describes the memory
+    /// ordering of this operation.
+    ///
+    /// [`CompareAndSetOrdering`]: trait.CompareAndSetOrdering.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{self as epoch, AtomicVersioned, Owned};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let a = AtomicVersioned::new(1234);
+    /// let guard = &epoch::pin();
+    ///
+    /// let curr = a.load(SeqCst, guard);
+    /// let new = a.compare_and_set(curr, Owned::new(5678), SeqCst, guard).unwrap();
+    /// assert_eq!(new.version(), 1);
+    /// unsafe { guard.defer_destroy(curr.ptr()); }
+    ///
+    /// let err = a.compare_and_set(curr, Owned::new(9012), SeqCst, guard).unwrap_err();
+    /// assert_eq!(err.current, new);
+    /// # unsafe { drop(new.ptr().into_owned()); } // avoid leak
+    /// ```
+    pub fn compare_and_set<'g, O, P>(
+        &self,
+        current: Versioned<T>,
+        new: P,
+        ord: O,
+        _: &'g Guard,
+    ) -> Result<Versioned<'g, T>, VersionedCompareAndSetError<'g, T, P>>
+    where
+        O: CompareAndSetOrdering,
+        P: Pointer<T>,
+    {
+        let current = [current.ptr.into_usize(), current.version];
+        let new = [new.into_usize(), current[1].wrapping_add(1)];
+        unsafe {
+            dw::compare_exchange(self.data.get(), current, new, ord.success(), ord.failure())
+                .map(|_| Versioned::from_data(new))
+                .map_err(|actual| VersionedCompareAndSetError {
+                    current: Versioned::from_data(actual),
+                    new: P::from_usize(new[0]),
+                })
+        }
+    }
+}
+
+impl<T: ?Sized + Pointable> fmt::Debug for AtomicVersioned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let current = self.load(Ordering::SeqCst, unsafe { unprotected() });
+
+        f.debug_struct("AtomicVersioned")
+            .field("ptr", &current.ptr)
+            .field("version", &current.version)
+            .finish()
+    }
+}
+
+impl<T: ?Sized + Pointable> Default for AtomicVersioned<T> {
+    fn default() -> Self {
+        AtomicVersioned::null()
+    }
+}
+
+impl<T: ?Sized + Pointable> From<Owned<T>> for AtomicVersioned<T> {
+    /// Returns a new versioned pointer pointing to `owned`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crossbeam_epoch::{AtomicVersioned, Owned};
+    ///
+    /// let a = AtomicVersioned::<i32>::from(Owned::new(1234));
+    /// ```
+    fn from(owned: Owned<T>) -> Self {
+        Self::from_usize(owned.into_usize())
+    }
+}
+
+impl<'g, T: ?Sized + Pointable> From<Shared<'g, T>> for AtomicVersioned<T> {
+    /// Returns a new versioned pointer pointing to `ptr`.
+    fn from(ptr: Shared<'g, T>) -> Self {
+        Self::from_usize(ptr.into_usize())
+    }
+}
+
+/// A pointer loaded from an [`AtomicVersioned`], together with its version.
+///
+/// [`AtomicVersioned`]: struct.AtomicVersioned.html
+pub struct Versioned<'g, T: 'g + ?Sized + Pointable> {
+    ptr: Shared<'g, T>,
+    version: usize,
+}
+
+impl<'g, T: ?Sized + Pointable> Versioned<'g, T> {
+    /// Returns the versioned pointer consisting of the tagged pointer and version in `data`.
+    unsafe fn from_data(data: [usize; 2]) -> Self {
+        Versioned {
+            ptr: Shared::from_usize(data[0]),
+            version: data[1],
+        }
+    }
+
+    /// Returns the pointer.
+    pub fn ptr(&self) -> Shared<'g, T> {
+        self.ptr
+    }
+
+    /// Returns the version of the pointer.
+    ///
+    /// The version is incremented on every update and wraps around on overflow.
+    pub fn version(&self) -> usize {
+        self.version
+    }
+}
+
+impl<'g, T: ?Sized + Pointable> Clone for Versioned<'g, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'g, T: ?Sized + Pointable> Copy for Versioned<'g, T> {}
+
+impl<'g, T: ?Sized + Pointable> PartialEq for Versioned<'g, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr && self.version == other.version
+    }
+}
+
+impl<'g, T: ?Sized + Pointable> Eq for Versioned<'g, T> {}
+
+impl<'g, T: ?Sized + Pointable> fmt::Debug for Versioned<'g, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Versioned")
+            .field("ptr", &self.ptr)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+/// The error returned on failed compare-and-set operation on an [`AtomicVersioned`].
+///
+/// [`AtomicVersioned`]: struct.AtomicVersioned.html
+pub struct VersionedCompareAndSetError<'g, T: ?Sized + Pointable + 'g, P: Pointer<T>> {
+    /// The value in the versioned pointer at the time of the failed operation.
+    pub current: Versioned<'g, T>,
+
+    /// The new value, which the operation failed to store.
+    pub new: P,
+}
+
+impl<'g, T, P> fmt::Debug for VersionedCompareAndSetError<'g, T, P>
+where
+    T: ?Sized + Pointable + 'g,
+    P: Pointer<T> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VersionedCompareAndSetError")
+            .field("current", &self.current)
+            .field("new", &self.new)
+            .finish()
+    }
+}
+
+/// Double-width atomic operations.
+mod dw {
+    use core::sync::atomic::Ordering;
+
+    /// Atomically loads the pair at `dst`.
+    #[inline]
+    pub unsafe fn load(dst: *mut [usize; 2], ord: Ordering) -> [usize; 2] {
+        // A compare-and-swap that replaces a value with itself is an atomic load.
+        match compare_exchange(dst, [0, 0], [0, 0], ord, ord) {
+            Ok(v) | Err(v) => v,
+        }
+    }
+
+    /// Atomically replaces the pair at `dst` with `new` if it equals `current`.
+    ///
+    /// Returns the previous pair, wrapped in `Ok` on success and in `Err` on failure.
+    #[inline]
+    pub unsafe fn compare_exchange(
+        dst: *mut [usize; 2],
+        current: [usize; 2],
+        new: [usize; 2],
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<[usize; 2], [usize; 2]> {
+        if native::is_supported() {
+            let previous = native::compare_exchange(dst, current, new);
+            if previous == current {
+                Ok(previous)
+            } else {
+                Err(previous)
+            }
+        } else {
+            super::locked::compare_exchange(dst, current, new, success, failure)
+        }
+    }
+
+    #[cfg(all(feature = "dwcas", target_arch = "x86_64"))]
+    mod native {
+        use core::arch::asm;
+
+        /// Returns `true` if the processor supports `cmpxchg16b`.
+        #[inline]
+        pub fn is_supported() -> bool {
+            #[cfg(feature = "use_std")]
+            {
+                is_x86_feature_detected!("cmpxchg16b")
+            }
+            #[cfg(not(feature = "use_std"))]
+            {
+                cfg!(target_feature = "cmpxchg16b")
+            }
+        }
+
+        /// Atomically replaces the pair at `dst` with `new` if it equals `current`, and returns
+        /// the previous pair. The operation is sequentially consistent.
+        #[inline]
+        pub unsafe fn compare_exchange(
+            dst: *mut [usize; 2],
+            current: [usize; 2],
+            new: [usize; 2],
+        ) -> [usize; 2] {
+            let (lo, hi): (usize, usize);
+            // `rbx` is reserved by LLVM, so the low half of `new` is swapped into it around the
+            // instruction.
+            asm!(
+                "xchg {new_lo}, rbx",
+                "lock cmpxchg16b xmmword ptr [{dst}]",
+                "mov rbx, {new_lo}",
+                dst = in(reg) dst,
+                new_lo = inout(reg) new[0] => _,
+                in("rcx") new[1],
+                inout("rax") current[0] => lo,
+                inout("rdx") current[1] => hi,
+                options(nostack),
+            );
+            [lo, hi]
+        }
+    }
+
+    #[cfg(all(feature = "dwcas", target_arch = "aarch64"))]
+    mod native {
+        use core::arch::asm;
+
+        /// Returns `true`, since every AArch64 processor supports exclusive load/store pairs.
+        #[inline]
+        pub fn is_supported() -> bool {
+            true
+        }
+
+        /// Atomically replaces the pair at `dst` with `new` if it equals `current`, and returns
+        /// the previous pair. The operation is sequentially consistent.
+        #[cfg(target_feature = "lse")]
+        #[inline]
+        pub unsafe fn compare_exchange(
+            dst: *mut [usize; 2],
+            current: [usize; 2],
+            new: [usize; 2],
+        ) -> [usize; 2] {
+            let (lo, hi): (usize, usize);
+            asm!(
+                "caspal x0, x1, x2, x3, [{dst}]",
+                dst = in(reg) dst,
+                inout("x0") current[0] => lo,
+                inout("x1") current[1] => hi,
+                in("x2") new[0],
+                in("x3") new[1],
+                options(nostack),
+            );
+            [lo, hi]
+        }
+
+        /// Atomically replaces the pair at `dst` with `new` if it equals `current`, and returns
+        /// the previous pair. The operation is sequentially consistent.
+        #[cfg(not(target_feature = "lse"))]
+        #[inline]
+        pub unsafe fn compare_exchange(
+            dst: *mut [usize; 2],
+            current: [usize; 2],
+            new: [usize; 2],
+        ) -> [usize; 2] {
+            let (lo, hi): (usize, usize);
+            // A pair loaded with `ldaxp` is only known to be read atomically once a store to it
+            // succeeds, so on mismatch the loaded pair is stored back.
+            asm!(
+                "2:",
+                "ldaxp {lo}, {hi}, [{dst}]",
+                "cmp {lo}, {current_lo}",
+                "ccmp {hi}, {current_hi}, #0, eq",
+                "csel {store_lo}, {new_lo}, {lo}, eq",
+                "csel {store_hi}, {new_hi}, {hi}, eq",
+                "stlxp {status:w}, {store_lo}, {store_hi}, [{dst}]",
+                "cbnz {status:w}, 2b",
+                dst = in(reg) dst,
+                current_lo = in(reg) current[0],
+                current_hi = in(reg) current[1],
+                new_lo = in(reg) new[0],
+                new_hi = in(reg) new[1],
+                lo = out(reg) lo,
+                hi = out(reg) hi,
+                store_lo = out(reg) _,
+                store_hi = out(reg) _,
+                status = out(reg) _,
+                options(nostack),
+            );
+            [lo, hi]
+        }
+    }
+
+    #[cfg(not(all(feature = "dwcas", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+    mod native {
+        /// Returns `false`, since there is no native implementation for this target.
+        #[inline]
+        pub fn is_supported() -> bool {
+            false
+        }
+
+        pub unsafe fn compare_exchange(
+            _: *mut [usize; 2],
+            _: [usize; 2],
+            _: [usize; 2],
+        ) -> [usize; 2] {
+            unreachable!()
+        }
+    }
+}
+
+/// The lock-based fallback for double-width atomic operations.
+mod locked {
+    use core::ptr;
+    use core::sync::atomic::{self, AtomicBool, Ordering};
+
+    /// The global table of spin locks, each protecting the pairs whose address hashes to it.
+    ///
+    /// Contention on a lock should be low, so a small table suffices.
+    static LOCKS: [AtomicBool; 16] = [
+        AtomicBool::new(false), AtomicBool::new(false), AtomicBool::new(false),
+        AtomicBool::new(false), AtomicBool::new(false), AtomicBool::new(false),
+        AtomicBool::new(false), AtomicBool::new(false), AtomicBool::new(false),
+        AtomicBool::new(false), AtomicBool::new(false), AtomicBool::new(false),
+        AtomicBool::new(false), AtomicBool::new(false), AtomicBool::new(false),
+        AtomicBool::new(false),
+    ];
+
+    /// A held lock, which is released when dropped.
+    struct LockGuard(&'static AtomicBool);
+
+    impl Drop for LockGuard {
+        fn drop(&mut self) {
+            self.0.store(false, Ordering::Release);
+        }
+    }
+
+    /// Acquires the lock protecting the pair at `dst`.
+    fn lock(dst: *mut [usize; 2]) -> LockGuard {
+        // Pairs are 16-byte aligned, so the lowest four bits of the address are always zero.
+        let lock = &LOCKS[(dst as usize >> 4) % LOCKS.len()];
+        while lock.swap(true, Ordering::Acquire) {
+            while lock.load(Ordering::Relaxed) {
+                #[allow(deprecated)]
+                atomic::spin_loop_hint();
+            }
+        }
+        LockGuard(lock)
+    }
+
+    /// Atomically replaces the pair at `dst` with `new` if it equals `current`.
+    ///
+    /// Returns the previous pair, wrapped in `Ok` on success and in `Err` on failure.
+    pub unsafe fn compare_exchange(
+        dst: *mut [usize; 2],
+        current: [usize; 2],
+        new: [usize; 2],
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<[usize; 2], [usize; 2]> {
+        let _guard = lock(dst);
+        let previous = ptr::read(dst);
+        if previous == current {
+            if success == Ordering::SeqCst {
+                atomic::fence(Ordering::SeqCst);
+            }
+            ptr::write(dst, new);
+            Ok(previous)
+        } else {
+            if failure == Ordering::SeqCst {
+                atomic::fence(Ordering::SeqCst);
+            }
+            Err(previous)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering::SeqCst;
+
+    use crossbeam_utils::thread;
+
+    use {pin, unprotected, Owned, Shared};
+    use super::{locked, AtomicVersioned};
+
+    #[test]
+    fn versions() {
+        let a = AtomicVersioned::new(1);
+        let guard = &pin();
+
+        let v0 = a.load(SeqCst, guard);
+        assert_eq!(v0.version(), 0);
+
+        let p = a.swap(Shared::null().with_tag(1), SeqCst, guard);
+        assert_eq!(p, v0);
+        let v1 = a.load(SeqCst, guard);
+        assert_eq!(v1.version(), 1);
+        assert!(v1.ptr().is_null());
+        assert_eq!(v1.ptr().tag(), 1);
+
+        // The pointer is restored, but the version has moved on.
+        a.store(v0.ptr(), SeqCst);
+        let v2 = a.load(SeqCst, guard);
+        assert_eq!(v2.ptr(), v0.ptr());
+        assert_eq!(v2.version(), 2);
+
+        let err = a.compare_and_set(v0, Shared::null(), SeqCst, guard).unwrap_err();
+        assert_eq!(err.current, v2);
+        let v3 = a.compare_and_set(v2, Shared::null(), SeqCst, guard).unwrap();
+        assert_eq!(v3.version(), 3);
+        assert_eq!(a.load(SeqCst, guard), v3);
+
+        unsafe { drop(v0.ptr().into_owned()) }
+    }
+
+    #[test]
+    fn wrapping_version() {
+        let a = AtomicVersioned::<i32>::null();
+        unsafe {
+            *a.data.get() = [0, usize::max_value()];
+            let guard = unprotected();
+            let v = a.load(SeqCst, guard);
+            assert_eq!(v.version(), usize::max_value());
+            let v = a.compare_and_set(v, Shared::null(), SeqCst, guard).unwrap();
+            assert_eq!(v.version(), 0);
+        }
+    }
+
+    #[test]
+    fn locked() {
+        let mut data = [1, 2];
+        unsafe {
+            let res = locked::compare_exchange(&mut data, [1, 3], [4, 5], SeqCst, SeqCst);
+            assert_eq!(res, Err([1, 2]));
+            let res = locked::compare_exchange(&mut data, [1, 2], [4, 5], SeqCst, SeqCst);
+            assert_eq!(res, Ok([1, 2]));
+        }
+        assert_eq!(data, [4, 5]);
+    }
+
+    #[test]
+    fn concurrent_increments() {
+        const COUNT: usize = 10_000;
+        const THREADS: usize = 4;
+
+        let a = AtomicVersioned::<i32>::null();
+
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| for _ in 0..COUNT {
+                    let guard = &pin();
+                    let mut current = a.load(SeqCst, guard);
+                    loop {
+                        let new = current.ptr().with_tag(current.ptr().tag() ^ 1);
+                        match a.compare_and_set(current, new, SeqCst, guard) {
+                            Ok(_) => break,
+                            Err(err) => current = err.current,
+                        }
+                    }
+                });
+            }
+        });
+
+        let guard = &pin();
+        let v = a.load(SeqCst, guard);
+        assert_eq!(v.version(), COUNT * THREADS);
+        assert_eq!(v.ptr().tag(), 0);
+    }
+
+    #[test]
+    fn owned() {
+        let a = AtomicVersioned::<[u8]>::init(vec![1, 2, 3]);
+        let guard = &pin();
+        let old = a.swap(Owned::<[u8]>::init(vec![4]), SeqCst, guard);
+        unsafe {
+            assert_eq!(old.ptr().deref(), &[1, 2, 3]);
+            assert_eq!(a.load(SeqCst, guard).ptr().deref(), &[4]);
+            drop(old.ptr().into_owned());
+            drop(a.load(SeqCst, guard).ptr().into_owned());
+        }
+    }
+}
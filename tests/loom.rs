@@ -0,0 +1,272 @@
+//! Loom models of the collector and the lock-free data structures.
+//!
+//! Run with:
+//!
+//! ```text
+//! RUSTFLAGS="--cfg crossbeam_loom" cargo test --features loom --test loom --release
+//! ```
+//!
+//! The number of preemptions explored per execution defaults to 2 and can be raised with the
+//! `LOOM_MAX_PREEMPTIONS` environment variable.
+
+#![cfg(crossbeam_loom)]
+
+#[macro_use]
+extern crate crossbeam_epoch as epoch;
+extern crate loom;
+
+use epoch::sync::list::{Entry, List};
+use epoch::sync::Queue;
+use epoch::{unprotected, Atomic, Collector, CollectorBuilder, Owned};
+use loom::cell::UnsafeCell;
+use loom::sync::atomic::AtomicUsize;
+use loom::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed};
+use loom::sync::Arc;
+use loom::thread;
+
+/// Checks `f` under every interleaving with a bounded number of preemptions.
+fn model<F>(f: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    let mut builder = loom::model::Builder::new();
+    if builder.preemption_bound.is_none() {
+        builder.preemption_bound = Some(2);
+    }
+    builder.check(f);
+}
+
+/// A value whose destruction races with readers unless the collector orders them.
+struct Value(UnsafeCell<usize>);
+
+unsafe impl Sync for Value {}
+
+impl Value {
+    fn new(value: usize) -> Value {
+        Value(UnsafeCell::new(value))
+    }
+
+    fn get(&self) -> usize {
+        self.0.with(|v| unsafe { *v })
+    }
+}
+
+impl Drop for Value {
+    fn drop(&mut self) {
+        self.0.with_mut(|v| unsafe { *v = 0 });
+    }
+}
+
+/// A pinned reader must be able to dereference an object that is concurrently unlinked and
+/// deferred for destruction, no matter how many times the writer tries to advance the epoch.
+#[test]
+fn pin_defer_collect() {
+    model(|| {
+        let collector = Collector::new();
+        let a = Arc::new(Atomic::new(Value::new(1)));
+
+        let reader = {
+            let collector = collector.clone();
+            let a = a.clone();
+            thread::spawn(move || {
+                let handle = collector.register();
+                let guard = &handle.pin();
+                let p = a.load(Acquire, guard);
+                let value = unsafe { p.deref() }.get();
+                assert!(value == 1 || value == 2);
+            })
+        };
+
+        let handle = collector.register();
+        {
+            let guard = &handle.pin();
+            let old = a.swap(Owned::new(Value::new(2)), AcqRel, guard);
+            unsafe { guard.defer_destroy(old) };
+        }
+        for _ in 0..3 {
+            handle.pin().flush();
+        }
+
+        reader.join().unwrap();
+        drop(handle);
+        unsafe { drop(a.load(Relaxed, unprotected()).into_owned()) };
+    });
+}
+
+/// Every deferred function is executed exactly once, even if participants register, defer and
+/// unregister concurrently.
+#[test]
+fn register_defer_unregister() {
+    model(|| {
+        let collector = Collector::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let collector = collector.clone();
+                let calls = calls.clone();
+                thread::spawn(move || {
+                    let handle = collector.register();
+                    let guard = handle.pin();
+                    guard.defer(move || calls.fetch_add(1, Relaxed));
+                    guard.flush();
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        drop(collector);
+        assert_eq!(calls.load(Relaxed), 2);
+    });
+}
+
+/// Participants freed by one thread can be taken over by another one, and the deferred functions
+/// of both are executed.
+#[test]
+fn recycle_locals() {
+    model(|| {
+        let collector = Collector::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let threads: Vec<_> = (1..3)
+            .map(|registrations| {
+                let collector = collector.clone();
+                let calls = calls.clone();
+                thread::spawn(move || {
+                    for _ in 0..registrations {
+                        let handle = collector.register();
+                        let calls = calls.clone();
+                        handle.pin().defer(move || calls.fetch_add(1, Relaxed));
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        drop(collector);
+        assert_eq!(calls.load(Relaxed), 3);
+    });
+}
+
+/// Participants of a collector with a fixed capacity are handed over between threads, and the
+/// deferred functions of both are executed.
+#[test]
+fn fixed_capacity_registry() {
+    model(|| {
+        let collector = CollectorBuilder::new().participant_capacity(1).build();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let collector = collector.clone();
+                let calls = calls.clone();
+                thread::spawn(move || {
+                    let handle = loop {
+                        match collector.try_register() {
+                            Ok(handle) => break handle,
+                            Err(_) => thread::yield_now(),
+                        }
+                    };
+                    handle.pin().defer(move || calls.fetch_add(1, Relaxed));
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        drop(collector);
+        assert_eq!(calls.load(Relaxed), 2);
+    });
+}
+
+/// Concurrent pushes and pops on `sync::Queue` neither lose nor duplicate elements.
+#[test]
+fn queue_push_pop() {
+    model(|| {
+        let collector = Collector::new();
+        let queue = Arc::new(Queue::with_collector(collector.clone()));
+
+        let producer = {
+            let collector = collector.clone();
+            let queue = queue.clone();
+            thread::spawn(move || {
+                let handle = collector.register();
+                queue.push(1, &handle.pin());
+                queue.push(2, &handle.pin());
+            })
+        };
+
+        let handle = collector.register();
+        let mut popped = Vec::new();
+        popped.extend(queue.try_pop(&handle.pin()));
+        popped.extend(queue.try_pop(&handle.pin()));
+        producer.join().unwrap();
+        popped.extend(queue.drain(&handle.pin()));
+
+        // The queue is FIFO and there is a single producer, so order is preserved.
+        assert_eq!(popped, [1, 2]);
+    });
+}
+
+struct Element {
+    entry: Entry,
+    value: usize,
+}
+
+impl_is_element!(Element, entry);
+
+/// Elements concurrently inserted into `sync::list::List` are all observed, except removed ones,
+/// and unlinking removed elements while another thread iterates is safe.
+#[test]
+fn list_push_remove_iter() {
+    model(|| {
+        let collector = Collector::new();
+        let list: Arc<List<Element>> = Arc::new(List::new());
+
+        let remover = {
+            let collector = collector.clone();
+            let list = list.clone();
+            thread::spawn(move || {
+                let handle = collector.register();
+                let guard = &handle.pin();
+                let element = list.push(
+                    Owned::new(Element {
+                        entry: Entry::default(),
+                        value: 1,
+                    }),
+                    guard,
+                );
+                assert!(list.remove(unsafe { element.deref() }, guard));
+            })
+        };
+
+        let handle = collector.register();
+        {
+            let guard = &handle.pin();
+            list.push(
+                Owned::new(Element {
+                    entry: Entry::default(),
+                    value: 2,
+                }),
+                guard,
+            );
+            for element in list.iter(guard).flatten() {
+                assert!(element.value == 1 || element.value == 2);
+            }
+        }
+        remover.join().unwrap();
+
+        let guard = &handle.pin();
+        let values: Vec<usize> = list
+            .iter(guard)
+            .filter_map(|e| e.ok())
+            .map(|e| e.value)
+            .collect();
+        assert_eq!(values, [2]);
+    });
+}